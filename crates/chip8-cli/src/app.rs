@@ -0,0 +1,246 @@
+//! Interactive terminal UI.
+
+use std::{io, path::Path, time::Duration};
+
+use chip8_db::RomProfile;
+use chip8_interpreter::{Chip8Emulator, render::Phosphor};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    text::Line,
+    widgets::{Block, Paragraph, Widget},
+};
+
+use crate::{
+    cheats::CheatPanel,
+    driver::Driver,
+    graphics::{Graphics, Protocol},
+    keymap::Keymap,
+    render::{Renderer, TextDisplay},
+    screenshot::{self, Camera},
+    video::Video,
+};
+
+/// What the side of the screen shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    RomInfo,
+    Cheats,
+    /// The cheat panel, taking keyboard input instead of the keypad
+    CheatsFocused,
+}
+
+#[derive(Debug)]
+pub struct App {
+    emulator: Chip8Emulator,
+    driver: Driver,
+    keymap: Keymap,
+    profile: Option<RomProfile>,
+    renderer: Renderer,
+    // Colours of the display, also used for screenshots
+    camera: Camera,
+    // Brightness of the pixels drawn, following the emulator display
+    phosphor: Phosphor,
+    // Output for renderers drawing pixels rather than text
+    graphics: Option<Graphics>,
+    // Recording of every frame, if asked for
+    video: Option<Video>,
+    // Outcome of the last screenshot, shown under the ROM info
+    status: Option<String>,
+    // Values frozen every frame and memory search
+    cheats: CheatPanel,
+    side: Side,
+    exit: bool,
+}
+
+impl App {
+    #[must_use]
+    #[allow(clippy::too_many_arguments)]
+    pub const fn new(
+        emulator: Chip8Emulator,
+        driver: Driver,
+        keymap: Keymap,
+        profile: Option<RomProfile>,
+        renderer: Renderer,
+        camera: Camera,
+        phosphor: Phosphor,
+        video: Option<Video>,
+        mut cheats: CheatPanel,
+    ) -> Self {
+        // Movies do not store cheats, so applying them would desync the recording or replay
+        if !matches!(driver, Driver::Live { .. }) {
+            cheats.disable();
+        }
+        let graphics = match renderer {
+            Renderer::Sixel => Some(Graphics::new(Protocol::Sixel)),
+            Renderer::Kitty => Some(Graphics::new(Protocol::Kitty)),
+            Renderer::Auto | Renderer::HalfBlock | Renderer::Braille => None,
+        };
+        Self {
+            emulator,
+            driver,
+            keymap,
+            profile,
+            renderer,
+            camera,
+            phosphor,
+            graphics,
+            video,
+            status: None,
+            cheats,
+            side: Side::RomInfo,
+            exit: false,
+        }
+    }
+    /// # Errors
+    /// - reading events
+    /// - fails to draw state to terminal
+    /// - a replay desyncs or the program faults
+    /// - saving a recording or video
+    pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.exit {
+            self.cheats.apply(&mut self.emulator);
+            self.driver
+                .run_frame(&mut self.emulator)
+                .map_err(io::Error::other)?;
+            let damage = self.emulator.take_display_damage();
+            let changed = self.phosphor.update(self.emulator.get_display(), damage);
+            if let Some(video) = &mut self.video {
+                video.capture(self.phosphor.intensities())?;
+            }
+            // Frames that drew nothing and fade nothing only need drawing after an event, such
+            // as the terminal being resized
+            if !self.handle_events()? && changed.is_empty() {
+                continue;
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Some(graphics) = &mut self.graphics {
+                graphics.present(
+                    &mut io::stdout(),
+                    self.phosphor.intensities(),
+                    self.camera.palette,
+                )?;
+            }
+        }
+        self.driver.finish()?;
+        self.video.map_or(Ok(()), Video::finish)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let vertical = Layout::horizontal([Constraint::Percentage(75), Constraint::Percentage(25)]);
+        let [emulator, info] = vertical.areas(frame.area());
+        self.draw_emu_display(frame, emulator);
+        match self.side {
+            Side::RomInfo => frame.render_widget(self.draw_rom_info(), info),
+            Side::Cheats | Side::CheatsFocused => frame.render_widget(
+                self.cheats
+                    .widget(&self.emulator, self.side == Side::CheatsFocused),
+                info,
+            ),
+        }
+    }
+
+    fn title(&self) -> &str {
+        self.profile
+            .as_ref()
+            .map_or("Chip8 Emulator", |profile| profile.title.as_str())
+    }
+
+    fn draw_rom_info(&self) -> impl Widget + '_ {
+        let mut lines = vec![];
+        if let Some(profile) = &self.profile {
+            if !profile.authors.is_empty() {
+                lines.push(Line::from(profile.authors.join(", ")));
+            }
+            lines.push(Line::from(profile.platform.to_string()));
+            lines.push(Line::default());
+            for (action, key) in &profile.keys {
+                lines.push(Line::from(format!("{key:X}: {action}")));
+            }
+        }
+        if let Some(status) = &self.status {
+            lines.push(Line::default());
+            lines.push(Line::from(status.as_str()));
+        }
+        Paragraph::new(lines).block(Block::bordered().title("ROM"))
+    }
+
+    /// Handle an event if one comes within a frame. Returns whether one did.
+    fn handle_events(&mut self) -> io::Result<bool> {
+        if !event::poll(Duration::from_millis(16))? {
+            return Ok(false);
+        }
+        if let Event::Key(key_event) = event::read()? {
+            let pressed = key_event.kind == KeyEventKind::Press;
+            self.handle_key_event(key_event, pressed);
+        }
+        Ok(true)
+    }
+    fn handle_key_event(&mut self, key_event: KeyEvent, pressed: bool) {
+        if key_event.code == KeyCode::F(2) && pressed {
+            self.side = match self.side {
+                Side::CheatsFocused => Side::Cheats,
+                Side::RomInfo | Side::Cheats => Side::CheatsFocused,
+            };
+            return;
+        }
+        if self.side == Side::CheatsFocused {
+            if key_event.code == KeyCode::Esc {
+                if pressed {
+                    self.side = Side::RomInfo;
+                }
+            } else if pressed {
+                self.cheats.key(key_event.code, &self.emulator);
+            }
+            return;
+        }
+        // Only on press, so releasing the Esc that closed the cheat panel does not also exit
+        if key_event.code == KeyCode::Esc && pressed {
+            self.exit();
+        }
+        if key_event.code == KeyCode::F(12) && pressed {
+            self.screenshot();
+        }
+        if let Some(idx) = self.keymap.lookup(key_event.code) {
+            self.driver.keypress(&mut self.emulator, idx, pressed);
+        }
+    }
+
+    /// Save the display to a new file in the working directory
+    fn screenshot(&mut self) {
+        let saved = screenshot::unused_path(Path::new(".")).and_then(|path| {
+            self.camera
+                .save(&path, &self.emulator, self.phosphor.intensities())
+                .map(|()| path)
+        });
+        self.status = Some(match saved {
+            Ok(path) => format!("Saved {}", path.display()),
+            Err(err) => format!("Screenshot failed: {err}"),
+        });
+    }
+
+    const fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    fn draw_emu_display(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(self.title());
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let intensities = self.phosphor.intensities();
+        if let Some(graphics) = &mut self.graphics {
+            frame.render_widget(graphics.reserve(inner), inner);
+        } else if self.renderer == Renderer::Braille {
+            frame.render_widget(
+                TextDisplay::braille(intensities, self.camera.palette),
+                inner,
+            );
+        } else {
+            frame.render_widget(
+                TextDisplay::half_blocks(intensities, self.camera.palette),
+                inner,
+            );
+        }
+    }
+}
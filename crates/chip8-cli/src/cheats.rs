@@ -0,0 +1,282 @@
+//! Cheat panel of the terminal UI: memory search and frozen values, saved per ROM.
+//!
+//! Cheats are kept in a TOML file with a table per ROM, keyed by its SHA-1 like keymaps, mapping
+//! locations to the values they are frozen at:
+//!
+//! ```toml
+//! [rom.0123456789abcdef0123456789abcdef01234567]
+//! V3 = 5
+//! 0x2F0 = 9
+//! ```
+
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use chip8_interpreter::{
+    Chip8Emulator, RomHash,
+    cheat::{self, Cheat, Comparison, Location, Search},
+};
+use ratatui::{
+    crossterm::event::KeyCode,
+    text::Line,
+    widgets::{Block, Paragraph, Widget},
+};
+use serde::{Deserialize, Serialize};
+
+/// Candidates listed in the panel, the rest are only counted
+const LISTED: usize = 8;
+
+const HELP: [&str; 4] = [
+    "new: start a search",
+    "same, changed, inc, dec, or a value: narrow",
+    "freeze LOC [VALUE], unfreeze LOC",
+    "F2: back to the game, Esc: close",
+];
+
+/// Location to frozen value, as written in the TOML file
+type CheatTable = BTreeMap<String, u8>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheatFile {
+    /// Cheats keyed by the SHA-1 hex digest of the ROM
+    #[serde(default)]
+    rom: BTreeMap<String, CheatTable>,
+}
+
+#[derive(Debug)]
+pub struct CheatPanel {
+    path: PathBuf,
+    rom: String,
+    file: CheatFile,
+    cheats: Vec<Cheat>,
+    // Off while an input movie records or replays, as movies do not store cheats
+    enabled: bool,
+    search: Option<Search>,
+    // Command being typed
+    input: String,
+    // Outcome of the last command
+    message: String,
+}
+
+impl CheatPanel {
+    /// Panel with the cheats saved for `rom` in the file at `path`, which need not exist yet
+    ///
+    /// # Errors
+    /// - reading or parsing the file
+    /// - a saved cheat has an invalid location
+    pub fn load(path: PathBuf, rom: &RomHash) -> io::Result<Self> {
+        let file: CheatFile = match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).map_err(io::Error::other)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => CheatFile::default(),
+            Err(err) => return Err(err),
+        };
+        let rom = rom.to_string();
+        let cheats = file
+            .rom
+            .get(&rom)
+            .into_iter()
+            .flatten()
+            .map(|(location, &value)| {
+                let location = location.parse().map_err(io::Error::other)?;
+                Ok(Cheat { location, value })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            path,
+            rom,
+            file,
+            cheats,
+            enabled: true,
+            search: None,
+            input: String::new(),
+            message: String::new(),
+        })
+    }
+
+    /// Stop applying frozen values, which can still be edited and saved
+    pub const fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Write the frozen values, before every frame
+    pub fn apply(&self, emulator: &mut Chip8Emulator) {
+        if self.enabled {
+            cheat::apply_all(&self.cheats, emulator);
+        }
+    }
+
+    /// Edit or run the command being typed
+    pub fn key(&mut self, code: KeyCode, emulator: &Chip8Emulator) {
+        match code {
+            KeyCode::Char(c) => self.input.push(c),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.input);
+                self.message = self
+                    .command(input.trim(), emulator)
+                    .unwrap_or_else(|err| err);
+            }
+            _ => {}
+        }
+    }
+
+    /// Run a command, returning what it did or what was wrong with it
+    fn command(&mut self, command: &str, emulator: &Chip8Emulator) -> Result<String, String> {
+        let mut words = command.split_whitespace();
+        let comparison = match words.next() {
+            None => return Ok(String::new()),
+            Some("new") => {
+                self.search = Some(Search::new(emulator));
+                return Ok("Searching all of memory".to_string());
+            }
+            Some("freeze") => {
+                let location = parse_location(words.next())?;
+                let value = match words.next() {
+                    Some(value) => parse_value(value)?,
+                    None => location.read(emulator),
+                };
+                self.cheats.retain(|cheat| cheat.location != location);
+                self.cheats.push(Cheat { location, value });
+                self.cheats.sort_by_key(|cheat| cheat.location);
+                return self.save(format!("Froze {location} at {value}"));
+            }
+            Some("unfreeze") => {
+                let location = parse_location(words.next())?;
+                self.cheats.retain(|cheat| cheat.location != location);
+                return self.save(format!("Unfroze {location}"));
+            }
+            Some("same") => Comparison::Equal,
+            Some("changed") => Comparison::Changed,
+            Some("inc") => Comparison::Increased,
+            Some("dec") => Comparison::Decreased,
+            Some(value) => Comparison::Value(parse_value(value)?),
+        };
+        let search = self
+            .search
+            .as_mut()
+            .ok_or("Start a search with `new` first")?;
+        search.narrow(emulator, comparison);
+        Ok(format!("{} candidates left", search.candidates().len()))
+    }
+
+    /// Store the cheats for this ROM in the file
+    fn save(&mut self, done: String) -> Result<String, String> {
+        let table = self
+            .cheats
+            .iter()
+            .map(|cheat| (cheat.location.to_string(), cheat.value))
+            .collect();
+        self.file.rom.insert(self.rom.clone(), table);
+        let contents = toml::to_string(&self.file).map_err(|err| err.to_string())?;
+        fs::write(&self.path, contents)
+            .map(|()| done)
+            .map_err(|err| format!("Saving {} failed: {err}", self.path.display()))
+    }
+
+    /// The panel, with help and a prompt when it has the keyboard
+    pub fn widget(&self, emulator: &Chip8Emulator, focused: bool) -> impl Widget + '_ {
+        let mut lines = vec![Line::from(if self.enabled {
+            "Frozen:"
+        } else {
+            "Frozen (off while a movie records or replays):"
+        })];
+        for cheat in &self.cheats {
+            lines.push(Line::from(format!(
+                "  {} = {}",
+                cheat.location, cheat.value
+            )));
+        }
+        if let Some(search) = &self.search {
+            let candidates = search.candidates();
+            lines.push(Line::default());
+            lines.push(Line::from(format!("Candidates: {}", candidates.len())));
+            for &location in candidates.iter().take(LISTED) {
+                lines.push(Line::from(format!(
+                    "  {:<5}  {:>3} -> {:>3}",
+                    location.to_string(),
+                    search.previous(location),
+                    location.read(emulator)
+                )));
+            }
+        }
+        lines.push(Line::default());
+        if !self.message.is_empty() {
+            lines.push(Line::from(self.message.as_str()));
+        }
+        if focused {
+            lines.push(Line::from(format!("> {}_", self.input)));
+            lines.push(Line::default());
+            lines.extend(HELP.map(Line::from));
+        } else {
+            lines.push(Line::from("F2: edit cheats"));
+        }
+        Paragraph::new(lines).block(Block::bordered().title("Cheats"))
+    }
+}
+
+fn parse_location(word: Option<&str>) -> Result<Location, String> {
+    word.ok_or("Expected a register or address")?
+        .parse::<Location>()
+        .map_err(|err| err.to_string())
+}
+
+/// A byte in decimal, or hex with `0x`
+fn parse_value(word: &str) -> Result<u8, String> {
+    word.strip_prefix("0x")
+        .map_or_else(|| word.parse(), |hex| u8::from_str_radix(hex, 16))
+        .map_err(|_| format!("{word:?} is not a command or a value 0-255"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(panel: &mut CheatPanel, emulator: &Chip8Emulator, command: &str) -> String {
+        for c in command.chars() {
+            panel.key(KeyCode::Char(c), emulator);
+        }
+        panel.key(KeyCode::Enter, emulator);
+        panel.message.clone()
+    }
+
+    #[test]
+    fn searches_freezes_and_saves() {
+        let path = std::env::temp_dir().join(format!("chip8-cheats-{}.toml", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut emulator = Chip8Emulator::new();
+        // ADD V1, 1; JP 200
+        emulator.load_data(&[0x71, 0x01, 0x12, 0x00]).unwrap();
+        let hash = emulator.rom_hash();
+        let mut panel = CheatPanel::load(path.clone(), &hash).unwrap();
+
+        assert_eq!(
+            run(&mut panel, &emulator, "inc"),
+            "Start a search with `new` first"
+        );
+        run(&mut panel, &emulator, "new");
+        emulator.run_frame(2);
+        assert_eq!(run(&mut panel, &emulator, "inc"), "1 candidates left");
+        assert_eq!(run(&mut panel, &emulator, "0x01"), "1 candidates left");
+        assert_eq!(
+            panel.search.as_ref().unwrap().candidates(),
+            [Location::Register(1)]
+        );
+        assert!(run(&mut panel, &emulator, "lots").contains("not a command"));
+
+        assert_eq!(run(&mut panel, &emulator, "freeze V1 9"), "Froze V1 at 9");
+        run(&mut panel, &emulator, "freeze 0x300");
+        panel.apply(&mut emulator);
+        assert_eq!(emulator.registers()[1], 9);
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, format!("[rom.{hash}]\n0x300 = 0\nV1 = 9\n"));
+        let mut reloaded = CheatPanel::load(path.clone(), &hash).unwrap();
+        assert_eq!(reloaded.cheats, panel.cheats);
+        reloaded.disable();
+        emulator.set_register(1, 2);
+        reloaded.apply(&mut emulator);
+        assert_eq!(emulator.registers()[1], 2);
+        fs::remove_file(&path).unwrap();
+    }
+}
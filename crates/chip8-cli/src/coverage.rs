@@ -0,0 +1,265 @@
+//! `headless --coverage`: which parts of a ROM a run executed, read and wrote, as an annotated
+//! disassembly in plain text or HTML.
+
+use std::{fmt::Write as _, fs, io, path::Path};
+
+use chip8_interpreter::{
+    analysis::{explore, load_range},
+    coverage::{Access, Coverage},
+    disasm::Instruction,
+};
+
+/// How a line of the listing was used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Executed,
+    /// Statically reachable code that never ran
+    Missed,
+    Data,
+}
+
+impl Kind {
+    const fn class(self) -> &'static str {
+        match self {
+            Self::Executed => "executed",
+            Self::Missed => "missed",
+            Self::Data => "data",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line {
+    addr: u16,
+    bytes: Vec<u8>,
+    kind: Kind,
+    access: Access,
+    text: String,
+}
+
+impl Line {
+    /// `XRW` flags, with `-` for the ways the bytes were not used
+    fn marks(&self) -> String {
+        [
+            (Access::EXECUTED, 'X'),
+            (Access::READ, 'R'),
+            (Access::WRITTEN, 'W'),
+        ]
+        .into_iter()
+        .map(|(access, mark)| {
+            if self.access.contains(access) {
+                mark
+            } else {
+                '-'
+            }
+        })
+        .collect()
+    }
+}
+
+/// Instructions, executed or statically reachable, and data bytes of the ROM, followed by any
+/// memory outside it the run used. Bytes come from the ROM image, or `memory` outside it.
+fn listing(rom: &[u8], memory: &[u8], coverage: &Coverage) -> Vec<Line> {
+    let code = explore(rom).instructions;
+    let range = load_range(rom);
+    let byte = |addr: usize| {
+        if range.contains(&addr) {
+            rom[addr - range.start]
+        } else {
+            memory[addr]
+        }
+    };
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < coverage.accesses().len() {
+        let access = coverage.accesses()[addr];
+        let in_rom = range.contains(&addr);
+        if !in_rom && access.is_empty() {
+            addr += 1;
+            continue;
+        }
+        let addr16 = u16::try_from(addr).expect("address in memory");
+        let starts_instruction =
+            access.contains(Access::OPCODE) || (in_rom && code.contains(&addr16));
+        if starts_instruction && addr + 1 < memory.len() {
+            let bytes = vec![byte(addr), byte(addr + 1)];
+            let instruction = Instruction::decode(u16::from_be_bytes([bytes[0], bytes[1]]));
+            lines.push(Line {
+                addr: addr16,
+                kind: if access.contains(Access::OPCODE) {
+                    Kind::Executed
+                } else {
+                    Kind::Missed
+                },
+                access: access | coverage.access(addr16 + 1),
+                text: instruction.to_string(),
+                bytes,
+            });
+            addr += 2;
+        } else {
+            let value = byte(addr);
+            lines.push(Line {
+                addr: addr16,
+                bytes: vec![value],
+                kind: Kind::Data,
+                access,
+                text: format!("DB {value:#04X}"),
+            });
+            addr += 1;
+        }
+    }
+    lines
+}
+
+fn summary(lines: &[Line], coverage: &Coverage) -> String {
+    let executed = lines
+        .iter()
+        .filter(|line| line.kind == Kind::Executed)
+        .count();
+    let code = executed
+        + lines
+            .iter()
+            .filter(|line| line.kind == Kind::Missed)
+            .count();
+    // A listing has at most one line per byte of RAM, so the counts convert exactly
+    let percent = match (u32::try_from(executed), u32::try_from(code)) {
+        (Ok(executed), Ok(code)) if code > 0 => f64::from(executed) * 100.0 / f64::from(code),
+        _ => 0.0,
+    };
+    format!(
+        "Instructions:  {executed} of {code} executed ({percent:.1}%)\n\
+         Bytes read:    {}\n\
+         Bytes written: {}\n",
+        coverage.count(Access::READ),
+        coverage.count(Access::WRITTEN)
+    )
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02X}");
+        out
+    })
+}
+
+/// Plain annotated disassembly
+fn text_report(rom: &[u8], memory: &[u8], coverage: &Coverage) -> String {
+    let lines = listing(rom, memory, coverage);
+    let mut out = summary(&lines, coverage);
+    let _ = writeln!(out, "\nListing:");
+    for line in &lines {
+        let note = if line.kind == Kind::Missed {
+            "  ; never run"
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "  {:#05X}  {:<4}  {}  {}{note}",
+            line.addr,
+            hex(&line.bytes),
+            line.marks(),
+            line.text
+        );
+    }
+    out
+}
+
+/// Self-contained page colouring lines by how they were used
+fn html_report(rom: &[u8], memory: &[u8], coverage: &Coverage) -> String {
+    let lines = listing(rom, memory, coverage);
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>CHIP-8 coverage</title>\n\
+         <style>\n\
+         body { font-family: monospace; }\n\
+         td { padding: 0 1em; white-space: pre; }\n\
+         .executed { background: #d4f7d4; }\n\
+         .missed { background: #f7d4d4; }\n\
+         .data.read { background: #d4e4f7; }\n\
+         .written { font-weight: bold; }\n\
+         </style>\n</head>\n<body>\n<pre>\n",
+    );
+    out.push_str(&summary(&lines, coverage));
+    out.push_str("</pre>\n<table>\n");
+    for line in &lines {
+        let mut class = line.kind.class().to_string();
+        if line.access.contains(Access::READ) {
+            class.push_str(" read");
+        }
+        if line.access.contains(Access::WRITTEN) {
+            class.push_str(" written");
+        }
+        let _ = writeln!(
+            out,
+            "<tr class=\"{class}\"><td>{:#05X}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            line.addr,
+            hex(&line.bytes),
+            line.marks(),
+            line.text
+        );
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+/// Write a report on `coverage` of `rom`, HTML if the path ends in `.html` or `.htm`
+///
+/// # Errors
+/// Writing the file
+pub fn write(path: &Path, rom: &[u8], memory: &[u8], coverage: &Coverage) -> io::Result<()> {
+    let html = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"));
+    let report = if html {
+        html_report(rom, memory, coverage)
+    } else {
+        text_report(rom, memory, coverage)
+    };
+    fs::write(path, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_interpreter::Chip8Emulator;
+
+    use super::*;
+
+    #[test]
+    fn annotates_listings() {
+        let rom = [
+            0xA2, 0x0A, // 200: LD I, 0x20A
+            0x30, 0x00, // 202: SE V0, 0
+            0x00, 0x00, // 204: never run
+            0xD0, 0x01, // 206: DRW V0, V0, 1
+            0x12, 0x08, // 208: JP 208
+            0x80, // 20A: sprite
+        ];
+        let mut emulator = Chip8Emulator::new();
+        emulator.load_data(&rom).unwrap();
+        let mut coverage = Coverage::new();
+        emulator.run_frame_with(6, &mut coverage);
+
+        let report = text_report(&rom, emulator.memory(), &coverage);
+        assert!(
+            report.starts_with("Instructions:  4 of 5 executed (80.0%)\nBytes read:    1\n"),
+            "{report}"
+        );
+        assert!(
+            report.contains(
+                "  0x202  3000  X--  SE V0, 0x00\n  0x204  0000  ---  HALT  ; never run\n"
+            ),
+            "{report}"
+        );
+        assert!(report.contains("  0x20A  80    -R-  DB 0x80\n"), "{report}");
+
+        let html = html_report(&rom, emulator.memory(), &coverage);
+        assert!(
+            html.contains("<tr class=\"missed\"><td>0x204</td>"),
+            "{html}"
+        );
+        assert!(
+            html.contains("<tr class=\"data read\"><td>0x20A</td>"),
+            "{html}"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Advances the emulator frame by frame, optionally recording or replaying an input movie.
+
+use std::{fs, io, path::PathBuf};
+
+use chip8_interpreter::{
+    Chip8Emulator, Chip8Hooks, NoHooks,
+    movie::{Movie, MovieError, Player, Recorder},
+};
+
+#[derive(clap::Args, Debug, Default)]
+pub struct MovieArgs {
+    /// Record keypad input to a movie file
+    #[arg(long, conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Play back a movie recorded with `--record`, ignoring keyboard input
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum Driver {
+    Live { ipf: u32 },
+    Record { recorder: Recorder, path: PathBuf },
+    Replay(Player),
+}
+
+impl Driver {
+    /// # Errors
+    /// - reading or parsing the movie to replay
+    /// - the movie was recorded with a different ROM
+    pub fn new(emulator: &mut Chip8Emulator, ipf: u32, args: &MovieArgs) -> io::Result<Self> {
+        if let Some(path) = &args.replay {
+            let movie = Movie::from_bytes(&fs::read(path)?).map_err(io::Error::other)?;
+            let player = Player::new(movie, emulator).map_err(io::Error::other)?;
+            Ok(Self::Replay(player))
+        } else if let Some(path) = &args.record {
+            Ok(Self::Record {
+                recorder: Recorder::new(emulator, ipf),
+                path: path.clone(),
+            })
+        } else {
+            Ok(Self::Live { ipf })
+        }
+    }
+
+    /// Forward a key change, unless input comes from a movie
+    pub fn keypress(&mut self, emulator: &mut Chip8Emulator, idx: usize, pressed: bool) {
+        match self {
+            Self::Live { .. } => emulator.keypress(idx, pressed),
+            Self::Record { recorder, .. } => recorder.keypress(emulator, idx, pressed),
+            Self::Replay(_) => {}
+        }
+    }
+
+    /// Run a frame. Returns `Ok(false)` when a replay has no frames left.
+    ///
+    /// # Errors
+    /// - a replay has desynced from its recording
+    /// - the program faults
+    pub fn run_frame(&mut self, emulator: &mut Chip8Emulator) -> Result<bool, MovieError> {
+        self.run_frame_with(emulator, &mut NoHooks)
+    }
+
+    /// [`Self::run_frame`] reporting what happens to `hooks`
+    ///
+    /// # Errors
+    /// - a replay has desynced from its recording
+    /// - the program faults
+    pub fn run_frame_with<H: Chip8Hooks>(
+        &mut self,
+        emulator: &mut Chip8Emulator,
+        hooks: &mut H,
+    ) -> Result<bool, MovieError> {
+        match self {
+            Self::Live { ipf } => {
+                emulator.try_run_frame_with(*ipf, hooks)?;
+                Ok(true)
+            }
+            Self::Record { recorder, .. } => {
+                recorder.run_frame_with(emulator, hooks)?;
+                Ok(true)
+            }
+            Self::Replay(player) => player.run_frame_with(emulator, hooks),
+        }
+    }
+
+    /// Save any recording
+    ///
+    /// # Errors
+    /// - writing the movie file
+    pub fn finish(self) -> io::Result<()> {
+        if let Self::Record { recorder, path } = self {
+            fs::write(path, recorder.finish().to_bytes())?;
+        }
+        Ok(())
+    }
+}
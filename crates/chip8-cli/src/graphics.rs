@@ -0,0 +1,336 @@
+//! Pixel-perfect output through the Sixel and Kitty graphics protocols.
+//!
+//! Ratatui only knows about text, so the display area is filled with cells marked to be skipped
+//! and the image is written over them after each frame is drawn.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chip8_interpreter::{SCREEN_HEIGHT, SCREEN_WIDTH, render::Palette};
+use flate2::{Compression, write::ZlibEncoder};
+use ratatui::{
+    buffer::Buffer,
+    crossterm::{
+        cursor::MoveTo,
+        queue,
+        terminal::{self, WindowSize},
+    },
+    layout::Rect,
+    widgets::Widget,
+};
+
+/// Cell size assumed when the terminal does not report its size in pixels
+const FALLBACK_CELL: (u16, u16) = (8, 16);
+
+/// Largest payload the Kitty protocol allows in one escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// Sixel colour registers used, shades from the palette's off colour to its on colour
+const SIXEL_SHADES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Sixel,
+    Kitty,
+}
+
+/// Where and at what scale the image goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Placement {
+    cells: Rect,
+    scale: u16,
+}
+
+#[derive(Debug)]
+pub struct Graphics {
+    protocol: Protocol,
+    placement: Option<Placement>,
+    // What the terminal is showing, so unchanged frames are not sent again
+    shown: Option<(Placement, Palette, Vec<u8>)>,
+}
+
+impl Graphics {
+    pub const fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            placement: None,
+            shown: None,
+        }
+    }
+
+    /// Reserve the cells of `area` the image will cover, centred and scaled to fit
+    pub fn reserve(&mut self, area: Rect) -> Reserve {
+        let cell = terminal::window_size()
+            .ok()
+            .and_then(|size| cell_size(&size))
+            .unwrap_or(FALLBACK_CELL);
+        let placement = place(area, cell);
+        self.placement = Some(placement);
+        Reserve(placement.cells)
+    }
+
+    /// Write the pixel intensities over the reserved cells, unless the terminal already shows them
+    ///
+    /// # Errors
+    /// Writing to the terminal
+    pub fn present(
+        &mut self,
+        out: &mut impl Write,
+        intensities: &[u8],
+        palette: Palette,
+    ) -> io::Result<()> {
+        let Some(placement) = self.placement else {
+            return Ok(());
+        };
+        if self
+            .shown
+            .as_ref()
+            .is_some_and(|(shown, shown_palette, pixels)| {
+                *shown == placement && *shown_palette == palette && pixels == intensities
+            })
+        {
+            return Ok(());
+        }
+        queue!(out, MoveTo(placement.cells.x, placement.cells.y))?;
+        let image = match self.protocol {
+            Protocol::Sixel => sixel(intensities, palette, placement.scale),
+            Protocol::Kitty => kitty(intensities, palette, placement.scale),
+        };
+        out.write_all(image.as_bytes())?;
+        out.flush()?;
+        self.shown = Some((placement, palette, intensities.to_vec()));
+        Ok(())
+    }
+}
+
+/// Cells skipped when ratatui draws, so they do not paint over the image
+pub struct Reserve(Rect);
+
+impl Widget for Reserve {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for y in self.0.top()..self.0.bottom() {
+            for x in self.0.left()..self.0.right() {
+                if area.contains((x, y).into()) {
+                    buf[(x, y)].set_skip(true);
+                }
+            }
+        }
+    }
+}
+
+/// Size of a cell in pixels, unless the terminal reports a zero or missing dimension
+fn cell_size(size: &WindowSize) -> Option<(u16, u16)> {
+    let width = size.width.checked_div(size.columns)?;
+    let height = size.height.checked_div(size.rows)?;
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Fit the display into `area` at a whole-number scale, given the size of a cell in pixels
+fn place(area: Rect, (cell_width, cell_height): (u16, u16)) -> Placement {
+    let scale_x = u32::from(area.width) * u32::from(cell_width) / SCREEN_WIDTH as u32;
+    let scale_y = u32::from(area.height) * u32::from(cell_height) / SCREEN_HEIGHT as u32;
+    let scale = scale_x.min(scale_y).max(1) as u16;
+    let width = (SCREEN_WIDTH as u16 * scale)
+        .div_ceil(cell_width)
+        .min(area.width);
+    let height = (SCREEN_HEIGHT as u16 * scale)
+        .div_ceil(cell_height)
+        .min(area.height);
+    Placement {
+        cells: Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        ),
+        scale,
+    }
+}
+
+fn intensity(intensities: &[u8], scale: usize, x: usize, y: usize) -> u8 {
+    intensities[y / scale * SCREEN_WIDTH + x / scale]
+}
+
+/// A DCS sequence drawing the display in [`SIXEL_SHADES`] colour registers, run-length encoded
+pub fn sixel(intensities: &[u8], palette: Palette, scale: u16) -> String {
+    let scale = usize::from(scale);
+    let (width, height) = (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+    let shade = |x, y| usize::from(intensity(intensities, scale, x, y)) * (SIXEL_SHADES - 1) / 255;
+    // Pixel aspect 1:1, background left alone as every pixel is painted
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for register in 0..SIXEL_SHADES {
+        let color = palette.color((register * 255 / (SIXEL_SHADES - 1)) as u8);
+        let percent = |channel: u8| (u32::from(channel) * 100 + 127) / 255;
+        let _ = write!(
+            out,
+            "#{register};2;{};{};{}",
+            percent(color.r),
+            percent(color.g),
+            percent(color.b)
+        );
+    }
+    for band in (0..height).step_by(6) {
+        let rows = 6.min(height - band);
+        let mut shades = [false; SIXEL_SHADES];
+        for x in 0..width {
+            for row in 0..rows {
+                shades[shade(x, band + row)] = true;
+            }
+        }
+        let mut first = true;
+        for register in (0..SIXEL_SHADES).filter(|&register| shades[register]) {
+            // Back to the start of the band for each colour after the first
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            let _ = write!(out, "#{register}");
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let mut bits = 0;
+                for row in 0..rows {
+                    if shade(x, band + row) == register {
+                        bits |= 1 << row;
+                    }
+                }
+                let sixel = char::from(63 + bits);
+                run = match run {
+                    Some((prev, count)) if prev == sixel => Some((prev, count + 1)),
+                    _ => {
+                        push_run(&mut out, run);
+                        Some((sixel, 1))
+                    }
+                };
+            }
+            push_run(&mut out, run);
+        }
+        if band + 6 < height {
+            out.push('-');
+        }
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_run(out: &mut String, run: Option<(char, usize)>) {
+    match run {
+        Some((sixel, count)) if count > 3 => {
+            let _ = write!(out, "!{count}{sixel}");
+        }
+        Some((sixel, count)) => out.extend(std::iter::repeat_n(sixel, count)),
+        None => {}
+    }
+}
+
+/// APC sequences transmitting the display as zlib-compressed RGB and placing it at the cursor,
+/// replacing the previous frame
+pub fn kitty(intensities: &[u8], palette: Palette, scale: u16) -> String {
+    let scale = usize::from(scale);
+    let (width, height) = (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+    let mut row = Vec::with_capacity(width * 3);
+    for y in 0..height {
+        row.clear();
+        for x in 0..width {
+            let color = palette.color(intensity(intensities, scale, x, y));
+            row.extend_from_slice(&[color.r, color.g, color.b]);
+        }
+        encoder
+            .write_all(&row)
+            .expect("writing to a Vec cannot fail");
+    }
+    let data = STANDARD.encode(encoder.finish().expect("writing to a Vec cannot fail"));
+
+    let mut out = String::new();
+    let mut chunks = data.as_bytes().chunks(KITTY_CHUNK).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = u8::from(chunks.peek().is_some());
+        out.push_str("\x1b_G");
+        if first {
+            // Image and placement ids are fixed so each frame replaces the last; quiet, and
+            // leave the cursor where it is
+            let _ = write!(out, "a=T,f=24,o=z,s={width},v={height},i=1,p=1,q=2,C=1,");
+            first = false;
+        }
+        let _ = write!(out, "m={more};");
+        out.push_str(std::str::from_utf8(chunk).expect("base64 is ASCII"));
+        out.push_str("\x1b\\");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
+    use super::*;
+
+    #[test]
+    fn places_at_whole_scales() {
+        let placement = place(Rect::new(1, 1, 100, 30), (8, 16));
+        // 800x480 pixels fit 64x32 at 12x, which takes 96 by 24 cells
+        assert_eq!(placement.scale, 12);
+        assert_eq!(placement.cells, Rect::new(3, 4, 96, 24));
+        assert_eq!(place(Rect::new(0, 0, 4, 2), (8, 16)).scale, 1);
+    }
+
+    #[test]
+    fn ignores_unusable_cell_sizes() {
+        let size = |width, height| WindowSize {
+            rows: 24,
+            columns: 80,
+            width,
+            height,
+        };
+        assert_eq!(cell_size(&size(640, 384)), Some((8, 16)));
+        assert_eq!(cell_size(&size(640, 0)), None);
+        assert_eq!(cell_size(&size(0, 384)), None);
+        // Narrower than one pixel per column
+        assert_eq!(cell_size(&size(40, 384)), None);
+        assert_eq!(
+            cell_size(&WindowSize {
+                rows: 0,
+                ..size(640, 384)
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn encodes_sixels() {
+        let mut display = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        display[0] = 255;
+        display[1] = 128;
+        let sixel = sixel(&display, Palette::CLASSIC, 1);
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;64;32#0;2;0;0;0#1;2;14;14;14"));
+        assert!(sixel.contains("#7;2;100;100;100#0"), "{sixel}");
+        // The first band: the top-left pixel is on, the one beside it half faded, the rest off
+        assert!(sixel.contains("#0}}!62~$#3?@!62?$#7@!63?-#0"), "{sixel}");
+        // Only the off colour in the last, two-row band
+        assert!(sixel.ends_with("-#0!64B\x1b\\"), "{sixel}");
+    }
+
+    #[test]
+    fn encodes_kitty_frames() {
+        let display = vec![255; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let kitty = kitty(&display, Palette::AMBER, 3);
+        assert!(kitty.starts_with("\x1b_Ga=T,f=24,o=z,s=192,v=96,i=1,p=1,q=2,C=1,m="));
+        let payload: String = kitty
+            .split("\x1b\\")
+            .filter_map(|chunk| chunk.split_once(';'))
+            .map(|(_, data)| data)
+            .collect();
+        let mut pixels = Vec::new();
+        ZlibDecoder::new(STANDARD.decode(payload).unwrap().as_slice())
+            .read_to_end(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels.len(), 192 * 96 * 3);
+        assert!(pixels.chunks(3).all(|pixel| pixel == [0xFF, 0xB0, 0x00]));
+    }
+}
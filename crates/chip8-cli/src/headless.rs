@@ -0,0 +1,90 @@
+//! Runs a ROM without a terminal UI, for scripted checks and reproducing recordings.
+
+use std::{io, path::PathBuf};
+
+use chip8_interpreter::{Chip8Emulator, coverage::Coverage, render::Phosphor};
+
+use crate::{coverage, driver::Driver, screenshot::Camera, video::Video};
+
+#[derive(clap::Args, Debug, Default)]
+pub struct HeadlessArgs {
+    /// Stop after this many frames, defaults to the length of the replay
+    #[arg(long, required_unless_present = "replay")]
+    frames: Option<u32>,
+    #[command(flatten)]
+    screenshot: ScreenshotArgs,
+    /// Write which bytes of memory ran, were read and written: HTML if the path ends in .html,
+    /// otherwise an annotated disassembly
+    #[arg(long, value_name = "PATH")]
+    coverage: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Default)]
+pub struct ScreenshotArgs {
+    /// Save a screenshot once this many frames have run
+    #[arg(long, value_name = "FRAME")]
+    screenshot_at: Option<u32>,
+    /// Where --screenshot-at saves: PNG, or PBM if the path ends in .pbm
+    #[arg(long, value_name = "PATH", default_value = "screenshot.png")]
+    screenshot: PathBuf,
+}
+
+/// # Errors
+/// - a replay desyncs or the program faults
+/// - saving a recording, screenshot, video or coverage report
+/// - the run ends before the screenshot frame
+pub fn run(
+    mut emulator: Chip8Emulator,
+    mut driver: Driver,
+    rom: &[u8],
+    args: &HeadlessArgs,
+    mut video: Option<Video>,
+    camera: Camera,
+    mut phosphor: Phosphor,
+) -> io::Result<()> {
+    let screenshot = &args.screenshot;
+    let mut coverage = args.coverage.as_ref().map(|_| Coverage::new());
+    // Following the display is only worth its cost when there are images to take
+    let capturing = screenshot.screenshot_at.is_some() || video.is_some();
+    let mut frame = 0;
+    loop {
+        if capturing {
+            let damage = emulator.take_display_damage();
+            phosphor.update(emulator.get_display(), damage);
+        }
+        if let Some(video) = &mut video {
+            video.capture(phosphor.intensities())?;
+        }
+        if screenshot.screenshot_at == Some(frame) {
+            camera.save(&screenshot.screenshot, &emulator, phosphor.intensities())?;
+            println!("screenshot: {}", screenshot.screenshot.display());
+        }
+        if args.frames.is_some_and(|limit| frame >= limit) {
+            break;
+        }
+        let running = match &mut coverage {
+            Some(coverage) => driver.run_frame_with(&mut emulator, coverage),
+            None => driver.run_frame(&mut emulator),
+        };
+        if !running.map_err(io::Error::other)? {
+            break;
+        }
+        frame += 1;
+    }
+    driver.finish()?;
+    if let Some(video) = video {
+        video.finish()?;
+    }
+    if let (Some(path), Some(coverage)) = (&args.coverage, &coverage) {
+        coverage::write(path, rom, emulator.memory(), coverage)?;
+        println!("coverage: {}", path.display());
+    }
+    println!("frames: {frame}");
+    println!("state:  {:016x}", emulator.state_hash());
+    match screenshot.screenshot_at {
+        Some(at) if at > frame => Err(io::Error::other(format!(
+            "stopped after {frame} frames, before the screenshot at frame {at}"
+        ))),
+        _ => Ok(()),
+    }
+}
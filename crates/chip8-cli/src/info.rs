@@ -0,0 +1,271 @@
+//! `info` subcommand: a static report on a ROM image.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Write as _},
+    io,
+};
+
+use chip8_db::RomDatabase;
+use chip8_interpreter::{
+    RomHash,
+    analysis::{explore, fits_in_memory, instruction_at, load_range},
+    detect::{Guess, detect},
+};
+use serde::Serialize;
+
+/// Instructions listed from the entry point
+const ENTRY_LISTING_LEN: usize = 16;
+
+#[derive(Debug, Serialize)]
+struct Report {
+    sha1: String,
+    size: usize,
+    title: Option<String>,
+    authors: Vec<String>,
+    /// `None` for an empty ROM
+    load_range: Option<Region>,
+    fits_in_memory: bool,
+    platform: GuessReport,
+    quirks: BTreeMap<&'static str, GuessReport>,
+    entry_point: Vec<Line>,
+    /// Occurrences of each opcode pattern in reachable code
+    opcodes: BTreeMap<&'static str, usize>,
+    subroutines: Vec<u16>,
+    sprites: Vec<Region>,
+}
+
+/// Inclusive address range
+#[derive(Debug, Serialize)]
+struct Region {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct GuessReport {
+    value: String,
+    confidence: String,
+}
+
+impl<T: Display> From<Guess<T>> for GuessReport {
+    fn from(guess: Guess<T>) -> Self {
+        Self {
+            value: guess.value.to_string(),
+            confidence: guess.confidence.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Line {
+    addr: u16,
+    opcode: u16,
+    text: String,
+}
+
+impl Report {
+    fn new(rom: &[u8], database: &RomDatabase) -> Self {
+        let hash = RomHash::of(rom);
+        let profile = database.lookup(&hash);
+        let detection = detect(rom);
+        let code = explore(rom);
+        let range = load_range(rom);
+
+        let entry_point = range
+            .clone()
+            .step_by(2)
+            .take(ENTRY_LISTING_LEN)
+            .filter_map(|addr| {
+                let addr = addr as u16;
+                let instruction = instruction_at(rom, addr)?;
+                let offset = addr as usize - range.start;
+                Some(Line {
+                    addr,
+                    opcode: u16::from_be_bytes([rom[offset], rom[offset + 1]]),
+                    text: instruction.to_string(),
+                })
+            })
+            .collect();
+
+        let mut opcodes = BTreeMap::new();
+        for &addr in &code.instructions {
+            if let Some(instruction) = instruction_at(rom, addr) {
+                *opcodes.entry(instruction.pattern()).or_default() += 1;
+            }
+        }
+
+        Self {
+            sha1: hash.to_string(),
+            size: rom.len(),
+            title: profile.map(|profile| profile.title.clone()),
+            authors: profile
+                .map(|profile| profile.authors.clone())
+                .unwrap_or_default(),
+            load_range: (!range.is_empty()).then(|| Region {
+                start: range.start,
+                end: range.end - 1,
+            }),
+            fits_in_memory: fits_in_memory(rom),
+            platform: detection.platform.into(),
+            quirks: BTreeMap::from([
+                ("shift", detection.shift.into()),
+                ("memory_increment", detection.memory_increment.into()),
+                ("jump", detection.jump.into()),
+                ("vf_reset", detection.vf_reset.into()),
+                ("wrap", detection.wrap.into()),
+            ]),
+            entry_point,
+            opcodes,
+            subroutines: code.subroutines.into_iter().collect(),
+            sprites: code
+                .sprites
+                .into_iter()
+                .map(|sprite| Region {
+                    start: sprite.start as usize,
+                    end: sprite.end as usize - 1,
+                })
+                .collect(),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut out = String::new();
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "Title:      {title}");
+        }
+        if !self.authors.is_empty() {
+            let _ = writeln!(out, "Authors:    {}", self.authors.join(", "));
+        }
+        let _ = writeln!(out, "SHA-1:      {}", self.sha1);
+        let _ = writeln!(out, "Size:       {} bytes", self.size);
+        match &self.load_range {
+            Some(range) => {
+                let _ = writeln!(
+                    out,
+                    "Load range: {:#05X}-{:#05X} ({})",
+                    range.start,
+                    range.end,
+                    if self.fits_in_memory {
+                        "fits"
+                    } else {
+                        "exceeds 0xFFF"
+                    }
+                );
+            }
+            None => {
+                let _ = writeln!(out, "Load range: none, the ROM is empty");
+            }
+        }
+        let _ = writeln!(
+            out,
+            "Platform:   {} ({} confidence)",
+            self.platform.value, self.platform.confidence
+        );
+
+        let _ = writeln!(out, "\nQuirks:");
+        for (name, guess) in &self.quirks {
+            let _ = writeln!(
+                out,
+                "  {name:<18}{} ({} confidence)",
+                guess.value, guess.confidence
+            );
+        }
+
+        let _ = writeln!(out, "\nEntry point:");
+        for line in &self.entry_point {
+            let _ = writeln!(
+                out,
+                "  {:#05X}  {:04X}  {}",
+                line.addr, line.opcode, line.text
+            );
+        }
+
+        let _ = writeln!(out, "\nOpcodes:");
+        for (pattern, count) in &self.opcodes {
+            let _ = writeln!(out, "  {pattern}  {count}");
+        }
+
+        let _ = writeln!(out, "\nSubroutines:");
+        for addr in &self.subroutines {
+            let _ = writeln!(out, "  {addr:#05X}");
+        }
+
+        let _ = writeln!(out, "\nSprite data:");
+        for sprite in &self.sprites {
+            let _ = writeln!(out, "  {:#05X}-{:#05X}", sprite.start, sprite.end);
+        }
+        out
+    }
+}
+
+/// # Errors
+/// - writing JSON to stdout
+pub fn print(rom: &[u8], database: &RomDatabase, json: bool) -> io::Result<()> {
+    let report = Report::new(rom, database);
+    if json {
+        serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+        println!();
+    } else {
+        print!("{}", report.text());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reports_a_small_rom() {
+        let rom = [
+            0x22, 0x08, // 200: CALL 0x208
+            0xA2, 0x0A, // 202: LD I, 0x20A
+            0xD0, 0x01, // 204: DRW V0, V0, 1
+            0x12, 0x06, // 206: JP 0x206
+            0x00, 0xEE, // 208: RET
+            0x80, // 20A: sprite
+        ];
+        let report = Report::new(&rom, &RomDatabase::default());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["sha1"], RomHash::of(&rom).to_string());
+        assert_eq!(json["size"], 11);
+        assert_eq!(json["load_range"], json!({ "start": 0x200, "end": 0x20A }));
+        assert_eq!(json["fits_in_memory"], true);
+        assert_eq!(
+            json["platform"],
+            json!({ "value": "CHIP-8", "confidence": "low" })
+        );
+        assert_eq!(
+            json["opcodes"],
+            json!({ "00EE": 1, "1nnn": 1, "2nnn": 1, "Annn": 1, "Dxyn": 1 })
+        );
+        assert_eq!(json["subroutines"], json!([0x208]));
+        assert_eq!(json["sprites"], json!([{ "start": 0x20A, "end": 0x20A }]));
+        assert_eq!(
+            json["entry_point"][0],
+            json!({ "addr": 0x200, "opcode": 0x2208, "text": "CALL 0x208" })
+        );
+
+        let text = report.text();
+        assert!(text.contains("Load range: 0x200-0x20A (fits)\n"), "{text}");
+        assert!(text.contains("\nSubroutines:\n  0x208\n"), "{text}");
+        assert!(text.contains("\nSprite data:\n  0x20A-0x20A\n"), "{text}");
+    }
+
+    #[test]
+    fn reports_no_load_range_for_an_empty_rom() {
+        let report = Report::new(&[], &RomDatabase::default());
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["load_range"],
+            json!(null)
+        );
+        assert!(
+            report
+                .text()
+                .contains("Load range: none, the ROM is empty\n")
+        );
+    }
+}
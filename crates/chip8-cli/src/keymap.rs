@@ -0,0 +1,292 @@
+//! Host keyboard to Chip-8 hex keypad mapping.
+//!
+//! Keymaps are loaded from a TOML file with a global `[default]` table and optional per-ROM
+//! overrides under `[rom.<sha1>]`. Each table maps a keypad key (`"0"`-`"F"`) to one or more
+//! host keys:
+//!
+//! ```toml
+//! [default]
+//! 5 = ["w", "Up"]
+//! 8 = ["s", "Down"]
+//!
+//! [rom.0123456789abcdef0123456789abcdef01234567]
+//! 1 = ["Up"]
+//! 4 = ["Down"]
+//! ```
+//!
+//! Keypad keys missing from a ROM override fall back to the `[default]` table, and keypad keys
+//! missing from both fall back to the built-in 1234/QWER/ASDF/ZXCV layout. Once layered, a host
+//! key may only be bound to one keypad key.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::Path,
+};
+
+use ratatui::crossterm::event::KeyCode;
+use serde::Deserialize;
+
+/// Built-in layout mirroring the COSMAC VIP keypad on the left-hand side of a QWERTY keyboard.
+const DEFAULT_LAYOUT: [(usize, char); 16] = [
+    (0x1, '1'),
+    (0x2, '2'),
+    (0x3, '3'),
+    (0xC, '4'),
+    (0x4, 'q'),
+    (0x5, 'w'),
+    (0x6, 'e'),
+    (0xD, 'r'),
+    (0x7, 'a'),
+    (0x8, 's'),
+    (0x9, 'd'),
+    (0xE, 'f'),
+    (0xA, 'z'),
+    (0x0, 'x'),
+    (0xB, 'c'),
+    (0xF, 'v'),
+];
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    InvalidKeypadKey(String),
+    InvalidHostKey(String),
+    /// One host key bound to two keypad keys
+    DuplicateHostKey(String),
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read keymap: {err}"),
+            Self::Parse(err) => write!(f, "failed to parse keymap: {err}"),
+            Self::InvalidKeypadKey(key) => write!(f, "{key:?} is not a keypad key (0-F)"),
+            Self::InvalidHostKey(key) => write!(f, "{key:?} is not a recognised host key"),
+            Self::DuplicateHostKey(key) => {
+                write!(f, "{key:?} is bound to more than one keypad key")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+impl From<io::Error> for KeymapError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for KeymapError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Parse(err)
+    }
+}
+
+/// Keypad key to host key names, as written in the TOML file
+type KeymapTable = HashMap<String, Vec<String>>;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct KeymapFile {
+    #[serde(default)]
+    default: KeymapTable,
+    /// Overrides keyed by the SHA-1 hex digest of the ROM
+    #[serde(default)]
+    rom: HashMap<String, KeymapTable>,
+}
+
+impl KeymapFile {
+    /// # Errors
+    /// - reading the file
+    /// - parsing the TOML
+    pub fn load(path: &Path) -> Result<Self, KeymapError> {
+        let contents = fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    /// # Errors
+    /// - invalid TOML or unexpected structure
+    pub fn parse(contents: &str) -> Result<Self, KeymapError> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Resolve the keymap for a ROM, layering its override on top of the global default.
+    ///
+    /// # Errors
+    /// - a table contains an unknown keypad or host key
+    /// - a host key is bound to two keypad keys once the tables are layered
+    pub fn resolve(&self, rom_hash: &str) -> Result<Keymap, KeymapError> {
+        let mut layers: BTreeMap<usize, &[String]> = BTreeMap::new();
+        let tables = [Some(&self.default), self.rom.get(rom_hash)];
+        for table in tables.into_iter().flatten() {
+            for (keypad, host_keys) in table {
+                layers.insert(parse_keypad_key(keypad)?, host_keys);
+            }
+        }
+
+        let mut keymap = Keymap::default();
+        let mut bound = HashMap::new();
+        for (idx, names) in layers {
+            keymap.bindings.retain(|_, bound| *bound != idx);
+            for name in names {
+                let code = parse_host_key(name)?;
+                if bound
+                    .insert(normalise(code), idx)
+                    .is_some_and(|other| other != idx)
+                {
+                    return Err(KeymapError::DuplicateHostKey(name.clone()));
+                }
+                keymap.bind(code, idx);
+            }
+        }
+        Ok(keymap)
+    }
+}
+
+/// Lookup table from host key to keypad index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<KeyCode, usize>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+        };
+        for (idx, c) in DEFAULT_LAYOUT {
+            keymap.bind(KeyCode::Char(c), idx);
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    fn bind(&mut self, code: KeyCode, idx: usize) {
+        self.bindings.insert(normalise(code), idx);
+    }
+
+    /// Keypad index bound to a host key, if any
+    #[must_use]
+    pub fn lookup(&self, code: KeyCode) -> Option<usize> {
+        self.bindings.get(&normalise(code)).copied()
+    }
+}
+
+/// Letters are matched case-insensitively so Shift/Caps Lock don't drop input
+const fn normalise(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        other => other,
+    }
+}
+
+fn parse_keypad_key(name: &str) -> Result<usize, KeymapError> {
+    match usize::from_str_radix(name, 16) {
+        Ok(idx) if idx < 16 && name.len() == 1 => Ok(idx),
+        _ => Err(KeymapError::InvalidKeypadKey(name.to_string())),
+    }
+}
+
+fn parse_host_key(name: &str) -> Result<KeyCode, KeymapError> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let code = match name.to_ascii_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" => KeyCode::Enter,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        f if f.starts_with('f') => f[1..]
+            .parse()
+            .ok()
+            .filter(|n| (1..=12).contains(n))
+            .map(KeyCode::F)
+            .ok_or_else(|| KeymapError::InvalidHostKey(name.to_string()))?,
+        _ => return Err(KeymapError::InvalidHostKey(name.to_string())),
+    };
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.lookup(KeyCode::Char('1')), Some(0x1));
+        assert_eq!(keymap.lookup(KeyCode::Char('V')), Some(0xF));
+        assert_eq!(keymap.lookup(KeyCode::Up), None);
+    }
+
+    #[test]
+    fn rom_override_layers_on_default() {
+        let file = KeymapFile::parse(
+            r#"
+            [default]
+            5 = ["z", "Up"]
+
+            [rom.abc]
+            5 = ["Right"]
+            8 = ["Down", "j"]
+            "#,
+        )
+        .unwrap();
+
+        let global = file.resolve("other").unwrap();
+        assert_eq!(global.lookup(KeyCode::Char('z')), Some(0x5));
+        assert_eq!(global.lookup(KeyCode::Up), Some(0x5));
+        assert_eq!(global.lookup(KeyCode::Char('w')), None);
+
+        let rom = file.resolve("abc").unwrap();
+        assert_eq!(rom.lookup(KeyCode::Right), Some(0x5));
+        assert_eq!(rom.lookup(KeyCode::Up), None);
+        assert_eq!(rom.lookup(KeyCode::Down), Some(0x8));
+        assert_eq!(rom.lookup(KeyCode::Char('J')), Some(0x8));
+        assert_eq!(rom.lookup(KeyCode::Char('1')), Some(0x1));
+    }
+
+    #[test]
+    fn rejects_host_keys_bound_twice() {
+        let file = KeymapFile::parse("[default]\n1 = [\"Up\"]\n2 = [\"j\", \"up\"]").unwrap();
+        assert!(matches!(
+            file.resolve(""),
+            Err(KeymapError::DuplicateHostKey(key)) if key == "up"
+        ));
+        // Across layers too, when the override leaves the default binding in place
+        let file = KeymapFile::parse("[default]\n1 = [\"J\"]\n[rom.abc]\n2 = [\"j\"]").unwrap();
+        assert!(file.resolve("other").is_ok());
+        assert!(matches!(
+            file.resolve("abc"),
+            Err(KeymapError::DuplicateHostKey(key)) if key == "j"
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let file = KeymapFile::parse("[default]\nG = [\"a\"]").unwrap();
+        assert!(matches!(
+            file.resolve(""),
+            Err(KeymapError::InvalidKeypadKey(_))
+        ));
+        let file = KeymapFile::parse("[default]\n1 = [\"Hyper\"]").unwrap();
+        assert!(matches!(
+            file.resolve(""),
+            Err(KeymapError::InvalidHostKey(_))
+        ));
+    }
+}
@@ -1,173 +1,246 @@
 #![allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
 
-use std::{io, time::Duration};
+mod app;
+mod cheats;
+mod coverage;
+mod driver;
+mod graphics;
+mod headless;
+mod info;
+mod keymap;
+mod profile;
+mod render;
+mod screenshot;
+mod script;
+mod video;
 
-use chip8_interpreter::{Chip8Emulator, SCREEN_WIDTH};
+use std::{borrow::Cow, fs, io, path::PathBuf};
+
+use app::App;
+use cheats::CheatPanel;
+use chip8_db::{RomDatabase, RomProfile};
+use chip8_interpreter::{
+    Chip8Emulator, RomHash,
+    cfg::Cfg,
+    detect::detect,
+    render::{Palette, Phosphor},
+};
 use clap::Parser;
 use clap::Subcommand;
-use itertools::Itertools;
+use driver::{Driver, MovieArgs};
+use headless::HeadlessArgs;
+use keymap::{Keymap, KeymapFile};
+use profile::ProfileArgs;
 use ratatui::crossterm::event::KeyboardEnhancementFlags;
 use ratatui::crossterm::event::PushKeyboardEnhancementFlags;
-use ratatui::{
-    DefaultTerminal, Frame,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
-    layout::{Constraint, Layout, Position, Rect},
-    style::Color,
-    symbols::Marker,
-    widgets::{
-        Block, Widget,
-        canvas::{Canvas, Points},
-    },
-};
+use render::Renderer;
+use screenshot::Camera;
+use video::{Video, VideoArgs};
+
+/// Instructions executed per 60Hz frame when the ROM database has no recommendation
+const DEFAULT_IPF: u32 = 10;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
     #[command(subcommand)]
     commands: Commands,
+    /// TOML file mapping host keys to the hex keypad
+    #[arg(long, global = true)]
+    keymap: Option<PathBuf>,
+    /// TOML file the cheat panel (F2) saves frozen values to, per ROM
+    #[arg(long, global = true, default_value = "cheats.toml")]
+    cheats: PathBuf,
+    /// Extra ROM profiles (TOML or JSON) layered over the bundled database
+    #[arg(long, global = true)]
+    database: Option<PathBuf>,
+    /// How to draw the display, detected from the terminal by default
+    #[arg(long, global = true, value_enum, default_value_t)]
+    renderer: Renderer,
+    /// Colours of the display: classic, amber, green, lcd, or unlit and lit colours like
+    /// "#000000,#ffffff"
+    #[arg(long, global = true, default_value = "classic")]
+    palette: Palette,
+    /// Fade pixels out over a few frames like a CRT, hiding sprite flicker
+    #[arg(long, global = true)]
+    phosphor: bool,
+    /// Image pixels along each side of a display pixel in screenshots and videos
+    #[arg(long, global = true, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..=64))]
+    scale: u32,
 }
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     Pong,
     Guess,
     Maze,
+    /// Play a ROM from disk in the terminal
+    Run {
+        rom: PathBuf,
+        #[command(flatten)]
+        movie: MovieArgs,
+        #[command(flatten)]
+        video: VideoArgs,
+    },
+    /// Run a ROM without a UI and print the final state hash
+    Headless {
+        rom: PathBuf,
+        #[command(flatten)]
+        headless: HeadlessArgs,
+        #[command(flatten)]
+        movie: MovieArgs,
+        #[command(flatten)]
+        video: VideoArgs,
+    },
+    /// Run a ROM without a UI and report where its instructions went
+    Profile {
+        rom: PathBuf,
+        #[command(flatten)]
+        profile: ProfileArgs,
+        #[command(flatten)]
+        movie: MovieArgs,
+    },
+    /// Run a Rhai script that loads ROMs and drives them without a UI, failing if any of its
+    /// assertions fail
+    Script {
+        script: PathBuf,
+    },
+    /// Report what is known or can be inferred about a ROM without running it
+    Info {
+        rom: PathBuf,
+        /// Emit the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the control-flow graph of a ROM in Graphviz DOT format
+    Cfg {
+        rom: PathBuf,
+    },
 }
 
-#[derive(Debug, Default)]
-pub struct App {
-    emulator: Chip8Emulator,
-    points: Vec<Position>,
-    exit: bool,
+impl Args {
+    const fn camera(&self) -> Camera {
+        Camera {
+            palette: self.palette,
+            scale: self.scale,
+        }
+    }
+
+    const fn phosphor(&self) -> Phosphor {
+        Phosphor::new(if self.phosphor {
+            Phosphor::PERSISTENCE
+        } else {
+            0
+        })
+    }
 }
 
 fn main() -> io::Result<()> {
-    let command = Args::parse().commands;
+    let args = Args::parse();
+    let mut database = RomDatabase::bundled();
+    if let Some(path) = &args.database {
+        database.extend_from_path(path).map_err(io::Error::other)?;
+    }
+    let (no_movie, no_video) = (MovieArgs::default(), VideoArgs::default());
+    let (rom, movie, video): (Cow<[u8]>, _, _) = match &args.commands {
+        Commands::Pong => (
+            include_bytes!("../../roms/PONG").into(),
+            &no_movie,
+            &no_video,
+        ),
+        Commands::Guess => (
+            include_bytes!("../../roms/GUESS").into(),
+            &no_movie,
+            &no_video,
+        ),
+        Commands::Maze => (
+            include_bytes!("../../roms/MAZE").into(),
+            &no_movie,
+            &no_video,
+        ),
+        Commands::Run { rom, movie, video } => (fs::read(rom)?.into(), movie, video),
+        Commands::Headless {
+            rom,
+            headless,
+            movie,
+            video,
+        } => {
+            let rom = fs::read(rom)?;
+            let profile = database.lookup(&RomHash::of(&rom));
+            let (mut emulator, ipf) = load(&rom, profile)?;
+            let driver = Driver::new(&mut emulator, ipf, movie)?;
+            let video = Video::new(video, args.camera())?;
+            return headless::run(
+                emulator,
+                driver,
+                &rom,
+                headless,
+                video,
+                args.camera(),
+                args.phosphor(),
+            );
+        }
+        Commands::Profile {
+            rom,
+            profile,
+            movie,
+        } => {
+            let rom = fs::read(rom)?;
+            let rom_profile = database.lookup(&RomHash::of(&rom));
+            let (mut emulator, ipf) = load(&rom, rom_profile)?;
+            let driver = Driver::new(&mut emulator, ipf, movie)?;
+            return profile::run(emulator, driver, profile);
+        }
+        Commands::Script { script } => return script::run(script, database),
+        Commands::Info { rom, json } => return info::print(&fs::read(rom)?, &database, *json),
+        Commands::Cfg { rom } => {
+            print!("{}", Cfg::build(&fs::read(rom)?).to_dot());
+            return Ok(());
+        }
+    };
+    let hash = RomHash::of(&rom);
+    let profile = database.lookup(&hash).cloned();
+    let keymap = match &args.keymap {
+        Some(path) => KeymapFile::load(path)
+            .and_then(|file| file.resolve(&hash.to_string()))
+            .map_err(io::Error::other)?,
+        None => Keymap::default(),
+    };
+    let (mut emulator, ipf) = load(&rom, profile.as_ref())?;
+    let driver = Driver::new(&mut emulator, ipf, movie)?;
+    let video = Video::new(video, args.camera())?;
+    let cheats = CheatPanel::load(args.cheats.clone(), &hash)?;
+
     let mut terminal = ratatui::init();
     ratatui::crossterm::execute!(
         io::stderr(),
         PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
     )?;
-    let app_result = App::new(&command).run(&mut terminal);
+    let renderer = args.renderer.resolve(|name| std::env::var(name).ok());
+    let app_result = App::new(
+        emulator,
+        driver,
+        keymap,
+        profile,
+        renderer,
+        args.camera(),
+        args.phosphor(),
+        video,
+        cheats,
+    )
+    .run(&mut terminal);
     ratatui::restore();
     app_result
 }
 
-impl App {
-    #[must_use]
-    pub fn new(command: &Commands) -> Self {
-        let pong = include_bytes!("../../roms/PONG");
-        let guess = include_bytes!("../../roms/GUESS");
-        let maze = include_bytes!("../../roms/MAZE");
-        let mut emulator = Chip8Emulator::new();
-        match command {
-            Commands::Pong => emulator.load_data(pong),
-            Commands::Guess => emulator.load_data(guess),
-            Commands::Maze => emulator.load_data(maze),
-        }
-        Self {
-            emulator,
-            exit: false,
-            points: vec![],
-        }
-    }
-    /// # Errors
-    /// - reading events
-    /// - fails to draw state to terminal
-    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        while !self.exit {
-            for _ in 0..10 {
-                self.emulator.tick();
-                self.calculate_points();
-            }
-            self.emulator.tick_timers();
-            self.handle_events()?;
-            terminal.draw(|frame| self.draw(frame))?;
-        }
-        Ok(())
-    }
-
-    fn draw(&self, frame: &mut Frame) {
-        let vertical = Layout::horizontal([Constraint::Percentage(75), Constraint::Percentage(25)]);
-        let [emulator, _] = vertical.areas(frame.area());
-        frame.render_widget(self.draw_emu_display(emulator), emulator);
-    }
-
-    fn handle_events(&mut self) -> io::Result<()> {
-        if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(key_event) = event::read()? {
-                let pressed = key_event.kind == KeyEventKind::Press;
-                self.handle_key_event(key_event, pressed);
-            }
-        }
-        Ok(())
-    }
-    fn handle_key_event(&mut self, key_event: KeyEvent, pressed: bool) {
-        if key_event.code == KeyCode::Esc {
-            self.exit();
-        }
-        let x = match key_event.code {
-            KeyCode::Char('1') => Some(0x1),
-            KeyCode::Char('2') => Some(0x2),
-            KeyCode::Char('3') => Some(0x3),
-            KeyCode::Char('4') => Some(0xC),
-            KeyCode::Char('q' | 'Q') => Some(0x4),
-            KeyCode::Char('w' | 'W') => Some(0x5),
-            KeyCode::Char('e' | 'E') => Some(0x6),
-            KeyCode::Char('r' | 'R') => Some(0xD),
-            KeyCode::Char('a' | 'A') => Some(0x7),
-            KeyCode::Char('s' | 'S') => Some(0x8),
-            KeyCode::Char('d' | 'D') => Some(0x9),
-            KeyCode::Char('f' | 'F') => Some(0xE),
-            KeyCode::Char('z' | 'Z') => Some(0xA),
-            KeyCode::Char('x' | 'X') => Some(0x0),
-            KeyCode::Char('c' | 'C') => Some(0xB),
-            KeyCode::Char('v' | 'V') => Some(0xF),
-            _ => None,
-        };
-
-        if let Some(idx) = x {
-            self.emulator.keypress(idx, pressed);
-        }
-    }
-
-    const fn exit(&mut self) {
-        self.exit = true;
-    }
-
-    fn calculate_points(&mut self) {
-        let display = self.emulator.get_display();
-        let mut points: Vec<Position> = Vec::new();
-        for (i, pixel) in display.iter().enumerate() {
-            if *pixel {
-                let x = (i % SCREEN_WIDTH) as u16;
-                let y = (i / SCREEN_WIDTH) as u16;
-                points.push(Position::new(x, y));
-            }
-        }
-        self.points = points;
-    }
-
-    fn draw_emu_display(&self, area: Rect) -> impl Widget + '_ {
-        Canvas::default()
-            .block(Block::bordered().title("Chip8 Emulator"))
-            .marker(Marker::Block)
-            .x_bounds([0.0, f64::from(area.width)])
-            .y_bounds([0.0, f64::from(area.height)])
-            .paint(move |ctx| {
-                let points = self
-                    .points
-                    .iter()
-                    .map(|p| {
-                        (
-                            f64::from(p.x) - f64::from(area.left()),
-                            f64::from(area.bottom()) - f64::from(p.y),
-                        )
-                    })
-                    .collect_vec();
-                ctx.draw(&Points {
-                    coords: &points,
-                    color: Color::White,
-                });
-            })
-    }
+/// Emulator with the ROM loaded and configured from its profile, or from static detection for
+/// unknown ROMs, along with the instructions to run per frame
+fn load(rom: &[u8], profile: Option<&RomProfile>) -> io::Result<(Chip8Emulator, u32)> {
+    let mut emulator = Chip8Emulator::new();
+    emulator.load_data(rom).map_err(io::Error::other)?;
+    let quirks = profile.map_or_else(|| detect(rom).quirks(), |profile| profile.quirks);
+    emulator.set_quirks(quirks);
+    let ipf = profile
+        .and_then(|profile| profile.ipf)
+        .unwrap_or(DEFAULT_IPF);
+    Ok((emulator, ipf))
 }
@@ -0,0 +1,181 @@
+//! `profile` subcommand: where a ROM spends its instructions over a number of frames.
+
+use std::{fmt::Write as _, fs, io, path::PathBuf};
+
+use chip8_interpreter::{
+    Chip8Emulator,
+    disasm::Instruction,
+    profile::{Profiler, SubroutineTime},
+};
+
+use crate::driver::Driver;
+
+#[derive(clap::Args, Debug)]
+pub struct ProfileArgs {
+    /// Frames to run, fewer if a replay ends first
+    #[arg(long)]
+    frames: u32,
+    /// Addresses listed as hot spots
+    #[arg(long, default_value_t = 20)]
+    top: usize,
+    /// Where to write folded stacks for flamegraph tools
+    #[arg(long, value_name = "PATH", default_value = "profile.folded")]
+    folded: PathBuf,
+}
+
+/// # Errors
+/// - a replay desyncs or the program faults
+/// - writing the folded stacks
+pub fn run(mut emulator: Chip8Emulator, mut driver: Driver, args: &ProfileArgs) -> io::Result<()> {
+    let mut profiler = Profiler::new();
+    for _ in 0..args.frames {
+        if !driver
+            .run_frame_with(&mut emulator, &mut profiler)
+            .map_err(io::Error::other)?
+        {
+            break;
+        }
+    }
+    print!("{}", report(&profiler, emulator.memory(), args.top));
+    fs::write(&args.folded, profiler.to_folded())?;
+    println!("\nFolded stacks written to {}", args.folded.display());
+    Ok(())
+}
+
+/// Share of all instructions, as a percentage to two decimal places
+fn percent(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    // Counts can outgrow what an f64 holds exactly, but hundredths of a percent fit in a u32
+    let hundredths = u128::from(count) * 10_000 / u128::from(total);
+    f64::from(u32::try_from(hundredths).unwrap_or(u32::MAX)) / 100.0
+}
+
+fn subroutine_name(addr: u16) -> String {
+    format!("sub_{addr:03X}")
+}
+
+/// Flat and call-tree report, with hot spots disassembled from `memory`
+fn report(profiler: &Profiler, memory: &[u8], top: usize) -> String {
+    let total = profiler.instructions();
+    let mut out = String::new();
+    let _ = writeln!(out, "Frames:       {}", profiler.frames());
+    let _ = writeln!(out, "Instructions: {total}");
+
+    let mut hot: Vec<(u16, u64)> = (0..)
+        .zip(profiler.executions().iter().copied())
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    hot.sort_by_key(|&(addr, count)| (std::cmp::Reverse(count), addr));
+    let _ = writeln!(out, "\nHot spots:");
+    for (addr, count) in hot.into_iter().take(top) {
+        let addr_idx = usize::from(addr);
+        let opcode = u16::from_be_bytes([memory[addr_idx], memory[addr_idx + 1]]);
+        let _ = writeln!(
+            out,
+            "  {addr:#05X}  {count:>10}  {:>5.1}%  {}",
+            percent(count, total),
+            Instruction::decode(opcode)
+        );
+    }
+
+    let mut patterns: Vec<_> = profiler.patterns().iter().collect();
+    patterns.sort_by_key(|&(pattern, count)| (std::cmp::Reverse(*count), *pattern));
+    let _ = writeln!(out, "\nOpcodes:");
+    for (pattern, &count) in patterns {
+        let _ = writeln!(
+            out,
+            "  {pattern}  {count:>10}  {:>5.1}%",
+            percent(count, total)
+        );
+    }
+
+    let mut subroutines = profiler.subroutines();
+    subroutines.sort_by_key(|time| (std::cmp::Reverse(time.total), time.addr));
+    let _ = writeln!(out, "\nSubroutines:         calls         own       total");
+    for SubroutineTime {
+        addr,
+        calls,
+        own,
+        total: inclusive,
+    } in subroutines
+    {
+        let _ = writeln!(
+            out,
+            "  {:<14}  {calls:>8}  {own:>10}  {inclusive:>10}  {:>5.1}%",
+            subroutine_name(addr),
+            percent(inclusive, total)
+        );
+    }
+
+    // Chains arrive after their callers, so each chain's time is the sum of those it prefixes
+    let paths: Vec<_> = profiler.call_paths().collect();
+    let _ = writeln!(out, "\nCall tree:");
+    for (idx, (path, own)) in paths.iter().enumerate() {
+        let inclusive: u64 = paths[idx..]
+            .iter()
+            .take_while(|(other, _)| other.starts_with(path))
+            .map(|(_, count)| count)
+            .sum();
+        let name = path
+            .last()
+            .map_or_else(|| "main".to_string(), |&addr| subroutine_name(addr));
+        let _ = writeln!(
+            out,
+            "  {:indent$}{name}  {:.1}% ({:.1}% own)",
+            "",
+            percent(inclusive, total),
+            percent(*own, total),
+            indent = path.len() * 2
+        );
+    }
+
+    if !profiler.wait_frames().is_empty() {
+        let _ = writeln!(out, "\nKey waits:");
+        for (addr, frames) in profiler.wait_frames() {
+            let _ = writeln!(out, "  {addr:#05X}  {frames} frames");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_flat_and_tree_profiles() {
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(&[
+                0x22, 0x04, // 200: CALL 204
+                0xF0, 0x0A, // 202: LD V0, K
+                0x60, 0x01, // 204: LD V0, 1
+                0x00, 0xEE, // 206: RET
+            ])
+            .unwrap();
+        let mut profiler = Profiler::new();
+        for _ in 0..2 {
+            emulator.run_frame_with(4, &mut profiler);
+        }
+        let report = report(&profiler, emulator.memory(), 2);
+        assert!(report.contains("Instructions: 8\n"), "{report}");
+        assert!(
+            report.contains("Hot spots:\n  0x202           5   62.5%  LD V0, K\n  0x200"),
+            "{report}"
+        );
+        assert!(report.contains("  Fx0A           5   62.5%\n"), "{report}");
+        assert!(
+            report.contains("  sub_204                1           2           2   25.0%"),
+            "{report}"
+        );
+        assert!(
+            report.contains(
+                "Call tree:\n  main  100.0% (75.0% own)\n    sub_204  25.0% (25.0% own)\n"
+            ),
+            "{report}"
+        );
+        assert!(report.contains("Key waits:\n  0x202  2 frames"), "{report}");
+    }
+}
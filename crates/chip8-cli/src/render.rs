@@ -0,0 +1,238 @@
+//! Drawing the 64x32 display in the terminal, scaled by a whole number so pixels stay square.
+//!
+//! Text renderers fill cells with block or Braille characters. Graphics protocols draw real
+//! pixels over cells ratatui is told to leave alone, see [`crate::graphics`].
+
+use chip8_interpreter::{
+    SCREEN_HEIGHT, SCREEN_WIDTH,
+    render::{Palette, Rgb},
+};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+/// Brightness from which a Braille dot is raised
+const BRAILLE_THRESHOLD: u8 = 0x80;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Renderer {
+    /// Pick the sharpest renderer the terminal is known to support
+    #[default]
+    Auto,
+    /// Two pixels per cell with `▀`/`▄`, exact on any terminal
+    HalfBlock,
+    /// Eight pixels per cell with Braille patterns, for small terminals
+    Braille,
+    /// Sixel graphics
+    Sixel,
+    /// Kitty graphics protocol, also spoken by `WezTerm` and Ghostty
+    Kitty,
+}
+
+impl Renderer {
+    /// Replace `Auto` with what the environment suggests the terminal supports
+    #[must_use]
+    pub fn resolve(self, env: impl Fn(&str) -> Option<String>) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+        let term = env("TERM").unwrap_or_default();
+        let program = env("TERM_PROGRAM").unwrap_or_default();
+        if env("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || matches!(program.as_str(), "WezTerm" | "ghostty")
+        {
+            Self::Kitty
+        } else if term.contains("sixel")
+            || term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || term == "yaft-256color"
+        {
+            Self::Sixel
+        } else {
+            Self::HalfBlock
+        }
+    }
+}
+
+/// Largest whole scale at which a `64 * dots_x` by `32 * dots_y` grid of dots fits in `area`,
+/// never less than 1
+pub fn fit(area: Rect, dots_x: u16, dots_y: u16) -> u16 {
+    let x = area.width * dots_x / SCREEN_WIDTH as u16;
+    let y = area.height * dots_y / SCREEN_HEIGHT as u16;
+    x.min(y).max(1)
+}
+
+pub const fn color(rgb: Rgb) -> Color {
+    Color::Rgb(rgb.r, rgb.g, rgb.b)
+}
+
+/// The display drawn with text, centred in the area it is rendered to
+pub struct TextDisplay<'a> {
+    /// Brightness of each pixel, see [`chip8_interpreter::render::Phosphor`]
+    intensities: &'a [u8],
+    palette: Palette,
+    braille: bool,
+}
+
+impl<'a> TextDisplay<'a> {
+    pub const fn half_blocks(intensities: &'a [u8], palette: Palette) -> Self {
+        Self {
+            intensities,
+            palette,
+            braille: false,
+        }
+    }
+
+    pub const fn braille(intensities: &'a [u8], palette: Palette) -> Self {
+        Self {
+            intensities,
+            palette,
+            braille: true,
+        }
+    }
+
+    fn intensity(&self, x: usize, y: usize) -> u8 {
+        if x < SCREEN_WIDTH && y < SCREEN_HEIGHT {
+            self.intensities[y * SCREEN_WIDTH + x]
+        } else {
+            0
+        }
+    }
+}
+
+impl Widget for TextDisplay<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // Dots per cell
+        let (dots_x, dots_y) = if self.braille { (2, 4) } else { (1, 2) };
+        let scale = fit(area, dots_x, dots_y);
+        let width = (SCREEN_WIDTH as u16 * scale).div_ceil(dots_x);
+        let height = (SCREEN_HEIGHT as u16 * scale).div_ceil(dots_y);
+        let left = area.x + area.width.saturating_sub(width) / 2;
+        let top = area.y + area.height.saturating_sub(height) / 2;
+        let scale = usize::from(scale);
+        let dot = |x: u16, y: u16| self.intensity(usize::from(x) / scale, usize::from(y) / scale);
+        let palette = self.palette;
+
+        for row in 0..height.min(area.height) {
+            for col in 0..width.min(area.width) {
+                let cell = &mut buf[(left + col, top + row)];
+                if self.braille {
+                    // Dots numbered down the left column then the right, with the bottom row last
+                    const BITS: [[u32; 2]; 4] =
+                        [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+                    // Braille cells have one colour, so it is the brightest dot's
+                    let (mut bits, mut brightest) = (0, 0);
+                    for (dy, row_bits) in BITS.iter().enumerate() {
+                        for (dx, bit) in row_bits.iter().enumerate() {
+                            let intensity = dot(col * 2 + dx as u16, row * 4 + dy as u16);
+                            if intensity >= BRAILLE_THRESHOLD {
+                                bits |= bit;
+                            }
+                            brightest = brightest.max(intensity);
+                        }
+                    }
+                    let symbol =
+                        char::from_u32(0x2800 + bits).expect("Braille patterns are U+2800-U+28FF");
+                    cell.set_char(symbol)
+                        .set_fg(color(palette.color(brightest)))
+                        .set_bg(color(palette.off));
+                } else {
+                    // The upper pixel is the foreground, the lower one the background
+                    cell.set_char('▀')
+                        .set_fg(color(palette.color(dot(col, row * 2))))
+                        .set_bg(color(palette.color(dot(col, row * 2 + 1))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = Color::Rgb(0xFF, 0xFF, 0xFF);
+    const BLACK: Color = Color::Rgb(0, 0, 0);
+
+    fn display(lit: &[(usize, usize, u8)]) -> Vec<u8> {
+        let mut display = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        for &(x, y, intensity) in lit {
+            display[y * SCREEN_WIDTH + x] = intensity;
+        }
+        display
+    }
+
+    /// Upper then lower pixel of each cell in a half-block row, `#` lit and `.` dark
+    fn halves(buf: &Buffer, y: u16, width: u16) -> String {
+        let pixel = |color| if color == WHITE { '#' } else { '.' };
+        (0..width)
+            .flat_map(|x| [pixel(buf[(x, y)].fg), pixel(buf[(x, y)].bg)])
+            .collect()
+    }
+
+    #[test]
+    fn half_blocks_map_two_pixels_to_a_cell() {
+        let display = display(&[
+            (0, 0, 255),
+            (1, 1, 255),
+            (2, 0, 255),
+            (2, 1, 255),
+            (3, 0, 128),
+        ]);
+        let area = Rect::new(0, 0, 64, 16);
+        let mut buf = Buffer::empty(area);
+        TextDisplay::half_blocks(&display, Palette::CLASSIC).render(area, &mut buf);
+        assert_eq!(buf[(0, 0)].symbol(), "▀");
+        assert_eq!(halves(&buf, 0, 3), "#..###");
+        assert_eq!(buf[(3, 0)].fg, Color::Rgb(0x80, 0x80, 0x80));
+
+        // Doubled when there is room, and centred
+        let area = Rect::new(0, 0, 130, 33);
+        let mut buf = Buffer::empty(area);
+        TextDisplay::half_blocks(&display, Palette::CLASSIC).render(area, &mut buf);
+        assert_eq!(halves(&buf, 0, 7), "..####....####");
+        assert_eq!(halves(&buf, 1, 7), "......########");
+    }
+
+    #[test]
+    fn braille_maps_eight_pixels_to_a_cell() {
+        let display = display(&[(0, 0, 255), (1, 3, 255), (2, 1, 255), (3, 0, 64)]);
+        let area = Rect::new(0, 0, 32, 8);
+        let mut buf = Buffer::empty(area);
+        TextDisplay::braille(&display, Palette::CLASSIC).render(area, &mut buf);
+        assert_eq!(buf[(0, 0)].symbol(), "⢁");
+        assert_eq!(buf[(1, 0)].symbol(), "⠂");
+        assert_eq!((buf[(0, 0)].fg, buf[(0, 0)].bg), (WHITE, BLACK));
+    }
+
+    #[test]
+    fn auto_detects_graphics_protocols() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| (*value).to_string())
+            }
+        };
+        assert_eq!(
+            Renderer::Auto.resolve(env(&[("TERM", "xterm-kitty")])),
+            Renderer::Kitty
+        );
+        assert_eq!(
+            Renderer::Auto.resolve(env(&[("TERM_PROGRAM", "WezTerm")])),
+            Renderer::Kitty
+        );
+        assert_eq!(
+            Renderer::Auto.resolve(env(&[("TERM", "foot")])),
+            Renderer::Sixel
+        );
+        assert_eq!(
+            Renderer::Auto.resolve(env(&[("TERM", "xterm-256color")])),
+            Renderer::HalfBlock
+        );
+        assert_eq!(
+            Renderer::Braille.resolve(env(&[("TERM", "xterm-kitty")])),
+            Renderer::Braille
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Saving the display as an image: PNG in the display's colours, or PBM for paths ending `.pbm`.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chip8_interpreter::{Chip8Emulator, SCREEN_HEIGHT, SCREEN_WIDTH, render::Palette};
+
+/// How screenshots look
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Camera {
+    pub palette: Palette,
+    /// Image pixels along each side of a display pixel
+    pub scale: u32,
+}
+
+impl Camera {
+    /// Size of the images taken, in pixels
+    #[must_use]
+    pub const fn size(self) -> (u32, u32) {
+        (
+            SCREEN_WIDTH as u32 * self.scale,
+            SCREEN_HEIGHT as u32 * self.scale,
+        )
+    }
+
+    /// Pixel intensities, see [`chip8_interpreter::render::Phosphor`], as scaled 8-bit RGB
+    #[must_use]
+    pub fn rgb(self, intensities: &[u8]) -> Vec<u8> {
+        self.scaled(intensities, |intensity| {
+            let color = self.palette.color(intensity);
+            [color.r, color.g, color.b]
+        })
+    }
+
+    /// Pixel intensities scaled, for images whose colour table holds a shade per intensity
+    #[must_use]
+    pub fn indexed(self, intensities: &[u8]) -> Vec<u8> {
+        self.scaled(intensities, |intensity| [intensity])
+    }
+
+    fn scaled<const N: usize>(self, intensities: &[u8], pixel: impl Fn(u8) -> [u8; N]) -> Vec<u8> {
+        let scale = self.scale as usize;
+        let (width, height) = self.size();
+        let line = width as usize * N;
+        let mut out = Vec::with_capacity(line * height as usize);
+        for row in intensities.chunks_exact(SCREEN_WIDTH) {
+            let start = out.len();
+            for &intensity in row {
+                let bytes = pixel(intensity);
+                for _ in 0..scale {
+                    out.extend_from_slice(&bytes);
+                }
+            }
+            for _ in 1..scale {
+                out.extend_from_within(start..start + line);
+            }
+        }
+        out
+    }
+
+    #[must_use]
+    pub fn png(self, intensities: &[u8]) -> Vec<u8> {
+        let (width, height) = self.size();
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .expect("writing to a Vec cannot fail");
+        writer
+            .write_image_data(&self.rgb(intensities))
+            .expect("the image is as large as the header says");
+        writer.finish().expect("writing to a Vec cannot fail");
+        out
+    }
+
+    /// Write the display to `path`. PBM images are black and white and show only what is lit
+    /// now, PNG images use the palette and `intensities`.
+    ///
+    /// # Errors
+    /// Writing the file
+    pub fn save(self, path: &Path, emulator: &Chip8Emulator, intensities: &[u8]) -> io::Result<()> {
+        let is_pbm = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pbm"));
+        let image = if is_pbm {
+            emulator.to_pbm(self.scale as usize)
+        } else {
+            self.png(intensities)
+        };
+        fs::write(path, image)
+    }
+}
+
+/// Screenshots numbered in one directory before giving up on finding a free name
+const MAX_SCREENSHOTS: u32 = 9999;
+
+/// The first of `screenshot-1.png`, `screenshot-2.png`, ... in `dir` that does not exist yet
+///
+/// # Errors
+/// Every name up to [`MAX_SCREENSHOTS`] is taken
+pub fn unused_path(dir: &Path) -> io::Result<PathBuf> {
+    (1..=MAX_SCREENSHOTS)
+        .map(|n| dir.join(format!("screenshot-{n}.png")))
+        .find(|path| !path.exists())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("screenshot-1.png to screenshot-{MAX_SCREENSHOTS}.png all exist"),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn encodes_scaled_pngs() {
+        let mut intensities = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        intensities[1] = 255;
+        let camera = Camera {
+            palette: Palette::AMBER,
+            scale: 2,
+        };
+        let png = camera.png(&intensities);
+
+        let mut reader = png::Decoder::new(Cursor::new(png)).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (128, 64));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        let pixel = |x: usize, y: usize| &pixels[(y * 128 + x) * 3..][..3];
+        assert_eq!(pixel(1, 1), [0x1A, 0x0E, 0x00]);
+        assert_eq!(pixel(2, 0), [0xFF, 0xB0, 0x00]);
+        assert_eq!(pixel(3, 1), [0xFF, 0xB0, 0x00]);
+        assert_eq!(pixel(4, 0), [0x1A, 0x0E, 0x00]);
+    }
+
+    #[test]
+    fn numbers_screenshots_after_existing_ones() {
+        let dir = std::env::temp_dir().join(format!("chip8-screenshots-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(unused_path(&dir).unwrap(), dir.join("screenshot-1.png"));
+        fs::write(dir.join("screenshot-1.png"), []).unwrap();
+        assert_eq!(unused_path(&dir).unwrap(), dir.join("screenshot-2.png"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
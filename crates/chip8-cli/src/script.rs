@@ -0,0 +1,414 @@
+//! `script` subcommand: Rhai scripts driving the emulator without a UI, for playthrough tests.
+//!
+//! Scripts load a ROM, run frames, press keys and check machine state with `assert`. ROM paths
+//! are relative to the script, so one in `scripts/` loads a bundled ROM like this:
+//!
+//! ```rhai
+//! load_rom("../roms/PONG");
+//! set_seed(1);
+//! breakpoint(0x2D4, |pc| print(`sub_2D4 called at frame ${frame()}`));
+//! run_frames(120);
+//! press(1);
+//! run_frames(10);
+//! release(1);
+//! assert(register(0xB) < 32, "paddle moved off screen");
+//! ```
+//!
+//! Failed assertions are reported and counted but do not stop the script; the run fails at the
+//! end if any did.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use chip8_db::RomDatabase;
+use chip8_interpreter::{Chip8Emulator, RomHash, SCREEN_HEIGHT, SCREEN_WIDTH};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, INT, ImmutableString, NativeCallContext};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// A loaded ROM and how to run it
+#[derive(Debug)]
+struct Machine {
+    emulator: Chip8Emulator,
+    ipf: u32,
+    frames: INT,
+}
+
+#[derive(Debug)]
+struct State {
+    database: RomDatabase,
+    // ROM paths are relative to the script
+    dir: PathBuf,
+    machine: Option<Machine>,
+    // Called before the instruction at each address runs
+    breakpoints: BTreeMap<u16, FnPtr>,
+    assertions: usize,
+    failures: usize,
+}
+
+impl State {
+    fn machine(&mut self) -> ScriptResult<&mut Machine> {
+        self.machine
+            .as_mut()
+            .ok_or_else(|| "no ROM loaded, call load_rom first".into())
+    }
+}
+
+type Shared = Rc<RefCell<State>>;
+
+/// Convert a script integer, naming what it was for when out of range
+fn convert<T: TryFrom<INT>>(value: INT, what: &str) -> ScriptResult<T> {
+    T::try_from(value).map_err(|_| format!("{value} is not a valid {what}").into())
+}
+
+fn key(value: INT) -> ScriptResult<usize> {
+    match convert(value, "key")? {
+        key @ 0..16 => Ok(key),
+        _ => Err(format!("{value} is not a key 0-15").into()),
+    }
+}
+
+/// Run a script, reporting how many of its assertions passed
+///
+/// # Errors
+/// - reading, compiling or running the script fails
+/// - any assertion failed
+pub fn run(path: &Path, database: RomDatabase) -> io::Result<()> {
+    let state = Rc::new(RefCell::new(State {
+        database,
+        dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        machine: None,
+        breakpoints: BTreeMap::new(),
+        assertions: 0,
+        failures: 0,
+    }));
+    let engine = engine(&state);
+    let ast = engine
+        .compile_file(path.to_path_buf())
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    engine
+        .run_ast(&ast)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+
+    let state = state.borrow();
+    println!(
+        "{} of {} assertions passed",
+        state.assertions - state.failures,
+        state.assertions
+    );
+    if state.failures > 0 {
+        return Err(io::Error::other(format!(
+            "{} assertions failed",
+            state.failures
+        )));
+    }
+    Ok(())
+}
+
+/// Engine with the emulator bindings, all acting on `state`
+fn engine(state: &Shared) -> Engine {
+    let mut engine = Engine::new();
+    register_running(&mut engine, state);
+    register_input(&mut engine, state);
+    register_reads(&mut engine, state);
+    register_breakpoints(&mut engine, state);
+    register_assertions(&mut engine, state);
+    engine
+}
+
+/// `load_rom`, `set_seed` and `run_frames`
+fn register_running(engine: &mut Engine, state: &Shared) {
+    let shared = state.clone();
+    engine.register_fn("load_rom", move |path: &str| -> ScriptResult<()> {
+        let mut state = shared.borrow_mut();
+        let path = state.dir.join(path);
+        let rom = fs::read(&path).map_err(|err| format!("reading {}: {err}", path.display()))?;
+        let profile = state.database.lookup(&RomHash::of(&rom));
+        let (emulator, ipf) = crate::load(&rom, profile).map_err(|err| err.to_string())?;
+        state.machine = Some(Machine {
+            emulator,
+            ipf,
+            frames: 0,
+        });
+        state.breakpoints.clear();
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("set_seed", move |seed: INT| -> ScriptResult<()> {
+        let seed = seed.cast_unsigned();
+        shared.borrow_mut().machine()?.emulator.set_seed(seed);
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn(
+        "run_frames",
+        move |context: NativeCallContext, frames: INT| run_frames(&shared, &context, frames),
+    );
+}
+
+/// `press` and `release` of keypad keys
+fn register_input(engine: &mut Engine, state: &Shared) {
+    let shared = state.clone();
+    engine.register_fn("press", move |value: INT| -> ScriptResult<()> {
+        let key = key(value)?;
+        shared.borrow_mut().machine()?.emulator.keypress(key, true);
+        Ok(())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("release", move |value: INT| -> ScriptResult<()> {
+        let key = key(value)?;
+        shared.borrow_mut().machine()?.emulator.keypress(key, false);
+        Ok(())
+    });
+}
+
+/// Registers, timers, memory, the display and frame count of the loaded machine
+fn register_reads(engine: &mut Engine, state: &Shared) {
+    let shared = state.clone();
+    engine.register_fn("register", move |x: INT| -> ScriptResult<INT> {
+        let x: usize = convert(x, "register")?;
+        let mut state = shared.borrow_mut();
+        let registers = state.machine()?.emulator.registers();
+        registers
+            .get(x)
+            .map(|&value| INT::from(value))
+            .ok_or_else(|| format!("{x} is not a register 0-15").into())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("i_register", move || -> ScriptResult<INT> {
+        Ok(INT::from(
+            shared.borrow_mut().machine()?.emulator.i_register(),
+        ))
+    });
+
+    let shared = state.clone();
+    engine.register_fn("pc", move || -> ScriptResult<INT> {
+        Ok(INT::from(
+            shared.borrow_mut().machine()?.emulator.program_counter(),
+        ))
+    });
+
+    let shared = state.clone();
+    engine.register_fn("delay_timer", move || -> ScriptResult<INT> {
+        Ok(INT::from(
+            shared.borrow_mut().machine()?.emulator.delay_timer(),
+        ))
+    });
+
+    let shared = state.clone();
+    engine.register_fn("sound_timer", move || -> ScriptResult<INT> {
+        Ok(INT::from(
+            shared.borrow_mut().machine()?.emulator.sound_timer(),
+        ))
+    });
+
+    let shared = state.clone();
+    engine.register_fn("memory", move |addr: INT| -> ScriptResult<INT> {
+        let addr: usize = convert(addr, "address")?;
+        let mut state = shared.borrow_mut();
+        let memory = state.machine()?.emulator.memory();
+        memory
+            .get(addr)
+            .map(|&value| INT::from(value))
+            .ok_or_else(|| format!("{addr:#X} is outside memory").into())
+    });
+
+    let shared = state.clone();
+    engine.register_fn("pixel", move |x: INT, y: INT| -> ScriptResult<bool> {
+        let (x, y): (usize, usize) = (convert(x, "column")?, convert(y, "row")?);
+        if x >= SCREEN_WIDTH || y >= SCREEN_HEIGHT {
+            return Err(format!("({x}, {y}) is off the display").into());
+        }
+        Ok(shared.borrow_mut().machine()?.emulator.pixel(x, y))
+    });
+
+    let shared = state.clone();
+    engine.register_fn("display", move || -> ScriptResult<String> {
+        let mut state = shared.borrow_mut();
+        let emulator = &state.machine()?.emulator;
+        let mut out = String::new();
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                out.push(if emulator.pixel(x, y) { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    });
+
+    let shared = state.clone();
+    engine.register_fn("frame", move || -> ScriptResult<INT> {
+        Ok(shared.borrow_mut().machine()?.frames)
+    });
+
+    let shared = state.clone();
+    engine.register_fn("state_hash", move || -> ScriptResult<String> {
+        let hash = shared.borrow_mut().machine()?.emulator.state_hash();
+        Ok(format!("{hash:016x}"))
+    });
+}
+
+/// `breakpoint` and `remove_breakpoint`, checked by `run_frames`
+fn register_breakpoints(engine: &mut Engine, state: &Shared) {
+    let shared = state.clone();
+    engine.register_fn(
+        "breakpoint",
+        move |addr: INT, callback: FnPtr| -> ScriptResult<()> {
+            let addr = convert(addr, "address")?;
+            shared.borrow_mut().breakpoints.insert(addr, callback);
+            Ok(())
+        },
+    );
+
+    let shared = state.clone();
+    engine.register_fn("remove_breakpoint", move |addr: INT| -> ScriptResult<()> {
+        let addr = convert(addr, "address")?;
+        shared.borrow_mut().breakpoints.remove(&addr);
+        Ok(())
+    });
+}
+
+/// `assert` and `assert_eq`, counted rather than stopping the script
+fn register_assertions(engine: &mut Engine, state: &Shared) {
+    let shared = state.clone();
+    engine.register_fn(
+        "assert",
+        move |context: NativeCallContext, passed: bool, message: &str| {
+            check(&shared, &context, passed, message);
+        },
+    );
+    register_assert_eq::<INT>(engine, state);
+    register_assert_eq::<bool>(engine, state);
+    register_assert_eq::<ImmutableString>(engine, state);
+}
+
+/// `assert_eq(actual, expected, message)` for values of type `T`
+fn register_assert_eq<T: PartialEq + Display + Clone + 'static>(
+    engine: &mut Engine,
+    state: &Shared,
+) {
+    let shared = state.clone();
+    engine.register_fn(
+        "assert_eq",
+        move |context: NativeCallContext, actual: T, expected: T, message: &str| {
+            let message = format!("{message}: expected {expected}, got {actual}");
+            check(&shared, &context, actual == expected, &message);
+        },
+    );
+}
+
+/// Count an assertion, reporting it with its line if it failed
+fn check(state: &Shared, context: &NativeCallContext, passed: bool, message: &str) {
+    let mut state = state.borrow_mut();
+    state.assertions += 1;
+    if !passed {
+        state.failures += 1;
+        let position = context.call_position();
+        match position.line() {
+            Some(line) => eprintln!("FAIL line {line}: {message}"),
+            None => eprintln!("FAIL: {message}"),
+        }
+    }
+}
+
+/// Run frames an instruction at a time, calling breakpoints before the instructions they are set
+/// on. Returns whether the program is still running rather than halted.
+fn run_frames(state: &Shared, context: &NativeCallContext, frames: INT) -> ScriptResult<bool> {
+    for _ in 0..frames {
+        let ipf = state.borrow_mut().machine()?.ipf;
+        for _ in 0..ipf {
+            let pc = state.borrow_mut().machine()?.emulator.program_counter();
+            // Released before calling, so the callback can use the bindings
+            let callback = state.borrow().breakpoints.get(&pc).cloned();
+            if let Some(callback) = callback {
+                let _: Dynamic = callback.call_within_context(context, (INT::from(pc),))?;
+            }
+            let mut state = state.borrow_mut();
+            let emulator = &mut state.machine()?.emulator;
+            if emulator
+                .try_tick()
+                .map_err(|err| err.to_string())?
+                .is_none()
+            {
+                // Stay on the halt, as frames run by the emulator do
+                emulator.set_program_counter(pc);
+                emulator.tick_timers();
+                state.machine()?.frames += 1;
+                return Ok(false);
+            }
+        }
+        let mut state = state.borrow_mut();
+        let machine = state.machine()?;
+        machine.emulator.tick_timers();
+        machine.frames += 1;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run `script` saved as `name` next to the ROM, in a directory of its own
+    fn run_script(name: &str, script: &str) -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("chip8-script-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("rom.ch8"), ROM)?;
+        let path = dir.join(name);
+        fs::write(&path, script)?;
+        let result = run(&path, RomDatabase::default());
+        fs::remove_dir_all(&dir)?;
+        result
+    }
+
+    const ROM: [u8; 10] = [
+        0x60, 0x05, // 200: LD V0, 5
+        0xF0, 0x15, // 202: LD DT, V0
+        0xE1, 0x9E, // 204: SKP V1
+        0x12, 0x04, // 206: JP 204
+        0x00, 0x00, // 208: halt
+    ];
+
+    #[test]
+    fn runs_frames_with_input_and_breakpoints() {
+        let script = r#"
+            load_rom("rom.ch8");
+            let hits = [];
+            breakpoint(0x208, |pc| hits.push(pc));
+            assert(run_frames(3), "still waiting for the key");
+            assert_eq(frame(), 3, "frames run");
+            assert_eq(register(0), 5, "V0");
+            assert_eq(delay_timer(), 2, "delay timer");
+            assert_eq(pc() >= 0x204, true, "looping");
+            press(0);
+            assert(!run_frames(1), "halted");
+            assert_eq(hits.len(), 1, "breakpoint hits");
+            assert_eq(hits[0], 0x208, "breakpoint address");
+            assert_eq(memory(0x200), 0x60, "ROM byte");
+            assert_eq(pixel(0, 0), false, "display");
+            assert_eq(display().len(), 65 * 32, "display text");
+        "#;
+        run_script("playthrough.rhai", script).unwrap();
+    }
+
+    #[test]
+    fn fails_on_failed_assertions() {
+        let script = "load_rom(\"rom.ch8\");\nassert_eq(register(0), 1, \"V0\");";
+        let err = run_script("fail.rhai", script).unwrap_err();
+        assert_eq!(err.to_string(), "1 assertions failed");
+        let err = run_script("key.rhai", "press(16);").unwrap_err();
+        assert!(err.to_string().contains("not a key"), "{err}");
+        let err = run_script("unloaded.rhai", "run_frames(1);").unwrap_err();
+        assert!(err.to_string().contains("no ROM loaded"), "{err}");
+    }
+}
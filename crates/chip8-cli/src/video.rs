@@ -0,0 +1,280 @@
+//! Recording the display to an animated GIF or APNG, a frame for every 60Hz tick.
+//!
+//! Frames are indexed images whose colour table holds a palette shade for each intensity, so
+//! phosphor trails survive without quantizing.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::screenshot::Camera;
+
+/// Shortest GIF frame browsers play at its real length, in hundredths of a second
+const GIF_MIN_DELAY: u64 = 2;
+
+#[derive(clap::Args, Debug, Default)]
+pub struct VideoArgs {
+    /// Record the display to an animated GIF, or an APNG if the path ends in .png or .apng
+    #[arg(long, value_name = "PATH")]
+    pub video: Option<PathBuf>,
+    /// Hold a frame for longer instead of repeating it when the display does not change
+    #[arg(long, requires = "video")]
+    pub dedup: bool,
+}
+
+enum Sink {
+    /// Written as frames come in
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        // Length of the frames written, in hundredths of a second
+        written: u64,
+    },
+    /// Kept until the end, as APNG headers need the number of frames
+    Apng {
+        path: PathBuf,
+        frames: Vec<(Vec<u8>, u16)>,
+    },
+}
+
+pub struct Video {
+    camera: Camera,
+    dedup: bool,
+    sink: Sink,
+    // The latest frame's intensities and ticks so far, not written until it is over
+    pending: Option<(Vec<u8>, u16)>,
+    // Ticks before the pending frame
+    ticks: u64,
+}
+
+impl Video {
+    /// Start recording if `args` ask for it
+    ///
+    /// # Errors
+    /// Creating the GIF file
+    pub fn new(args: &VideoArgs, camera: Camera) -> io::Result<Option<Self>> {
+        let Some(path) = &args.video else {
+            return Ok(None);
+        };
+        let sink = if is_apng(path) {
+            Sink::Apng {
+                path: path.clone(),
+                frames: Vec::new(),
+            }
+        } else {
+            let (width, height) = camera.size();
+            let mut encoder = gif::Encoder::new(
+                BufWriter::new(File::create(path)?),
+                width as u16,
+                height as u16,
+                &color_table(camera),
+            )
+            .map_err(io::Error::other)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(io::Error::other)?;
+            Sink::Gif {
+                encoder,
+                written: 0,
+            }
+        };
+        Ok(Some(Self {
+            camera,
+            dedup: args.dedup,
+            sink,
+            pending: None,
+            ticks: 0,
+        }))
+    }
+
+    /// Add a tick showing `intensities`, see [`chip8_interpreter::render::Phosphor`]
+    ///
+    /// # Errors
+    /// Writing the previous frame
+    pub fn capture(&mut self, intensities: &[u8]) -> io::Result<()> {
+        if let Some((pixels, ticks)) = &mut self.pending
+            && self.dedup
+            && *ticks < u16::MAX
+            && pixels == intensities
+        {
+            *ticks += 1;
+            return Ok(());
+        }
+        if let Some(frame) = self.pending.replace((intensities.to_vec(), 1)) {
+            self.write(frame, false)?;
+        }
+        Ok(())
+    }
+
+    /// Write the last frame and finish the file
+    ///
+    /// # Errors
+    /// - writing the file
+    /// - nothing was captured, for APNG
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(frame) = self.pending.take() {
+            self.write(frame, true)?;
+        }
+        match self.sink {
+            Sink::Gif { encoder, .. } => {
+                encoder.into_inner().map_err(io::Error::other)?;
+                Ok(())
+            }
+            Sink::Apng { path, frames } => apng(&path, self.camera, &frames),
+        }
+    }
+
+    fn write(&mut self, (intensities, ticks): (Vec<u8>, u16), last: bool) -> io::Result<()> {
+        self.ticks += u64::from(ticks);
+        match &mut self.sink {
+            Sink::Gif { encoder, written } => {
+                // Rounded to GIF's hundredths of a second from where the frame ends, so rounding
+                // errors do not add up. Frames too short to play right are left out and the next
+                // one shown for their time.
+                let delay = (self.ticks * 100 + 30) / 60 - *written;
+                if delay < GIF_MIN_DELAY && !last {
+                    return Ok(());
+                }
+                let delay = delay.clamp(GIF_MIN_DELAY, u64::from(u16::MAX));
+                let (width, height) = self.camera.size();
+                let mut frame = gif::Frame::from_indexed_pixels(
+                    width as u16,
+                    height as u16,
+                    self.camera.indexed(&intensities),
+                    None,
+                );
+                frame.delay = delay as u16;
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+                *written += delay;
+            }
+            Sink::Apng { frames, .. } => frames.push((intensities, ticks)),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Video {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Video")
+            .field("camera", &self.camera)
+            .field("dedup", &self.dedup)
+            .field("ticks", &self.ticks)
+            .finish_non_exhaustive()
+    }
+}
+
+fn is_apng(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("png") || extension.eq_ignore_ascii_case("apng")
+    })
+}
+
+/// RGB for each intensity, in order
+fn color_table(camera: Camera) -> Vec<u8> {
+    (0..=u8::MAX)
+        .flat_map(|intensity| {
+            let color = camera.palette.color(intensity);
+            [color.r, color.g, color.b]
+        })
+        .collect()
+}
+
+fn apng(path: &Path, camera: Camera, frames: &[(Vec<u8>, u16)]) -> io::Result<()> {
+    let (width, height) = camera.size();
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(color_table(camera));
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(io::Error::other)?;
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for (intensities, ticks) in frames {
+        writer
+            .set_frame_delay(*ticks, 60)
+            .map_err(io::Error::other)?;
+        writer
+            .write_image_data(&camera.indexed(intensities))
+            .map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chip8_interpreter::{SCREEN_HEIGHT, SCREEN_WIDTH, render::Palette};
+
+    use super::*;
+
+    const PIXELS: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+    fn record(path: &Path, dedup: bool, frames: &[u8]) {
+        let args = VideoArgs {
+            video: Some(path.to_path_buf()),
+            dedup,
+        };
+        let camera = Camera {
+            palette: Palette::GREEN,
+            scale: 2,
+        };
+        let mut video = Video::new(&args, camera).unwrap().unwrap();
+        for &intensity in frames {
+            video.capture(&[intensity; PIXELS]).unwrap();
+        }
+        video.finish().unwrap();
+    }
+
+    #[test]
+    fn records_gifs() {
+        let path = std::env::temp_dir().join(format!("chip8-{}.gif", std::process::id()));
+        record(&path, false, &[0, 0, 0, 255, 255, 255]);
+        let file = File::open(&path).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(file).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (128, 64));
+        let mut delays = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push((frame.delay, frame.buffer[0]));
+        }
+        // Six 1/60s ticks are 10/100s, and frames under 2/100s are merged into the next
+        assert_eq!(delays, [(2, 0), (3, 0), (2, 255), (3, 255)]);
+
+        record(&path, true, &[0, 0, 0, 255, 255, 255]);
+        let file = File::open(&path).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(file).unwrap();
+        let mut delays = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push((frame.delay, frame.buffer[0]));
+        }
+        assert_eq!(delays, [(5, 0), (5, 255)]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn records_apngs() {
+        let path = std::env::temp_dir().join(format!("chip8-{}.apng", std::process::id()));
+        record(&path, true, &[0, 0, 128, 128, 128, 0]);
+        let decoder = png::Decoder::new(io::BufReader::new(File::open(&path).unwrap()));
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (128, 64));
+        assert_eq!(info.animation_control.unwrap().num_frames, 3);
+        let green = Palette::GREEN.color(128);
+        assert_eq!(
+            info.palette.as_ref().unwrap()[128 * 3..][..3],
+            [green.r, green.g, green.b]
+        );
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let mut frames = vec![];
+        for _ in 0..3 {
+            reader.next_frame(&mut pixels).unwrap();
+            let control = reader.info().frame_control.unwrap();
+            frames.push((control.delay_num, control.delay_den, pixels[0]));
+        }
+        assert_eq!(frames, [(2, 60, 0), (3, 60, 128), (1, 60, 0)]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+//! Per-ROM settings database keyed by the SHA-1 of the ROM image.
+//!
+//! A small set of profiles is bundled with the crate and can be extended or overridden with
+//! user supplied TOML or JSON files using the same schema as `database.toml`.
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use chip8_interpreter::{ParseRomHashError, Platform, Quirks, RomHash};
+use serde::Deserialize;
+
+const BUNDLED: &str = include_str!("../database.toml");
+
+#[derive(Debug)]
+pub enum DbError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    InvalidHash(String, ParseRomHashError),
+    UnknownPlatform(String),
+    InvalidKey(String, u8),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read database: {err}"),
+            Self::Toml(err) => write!(f, "failed to parse database: {err}"),
+            Self::Json(err) => write!(f, "failed to parse database: {err}"),
+            Self::InvalidHash(hash, err) => write!(f, "{hash:?}: {err}"),
+            Self::UnknownPlatform(platform) => write!(f, "{platform:?} is not a known platform"),
+            Self::InvalidKey(action, key) => {
+                write!(f, "{action:?} is bound to {key:#x}, outside the keypad")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+impl From<io::Error> for DbError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for DbError {
+    fn from(err: toml::de::Error) -> Self {
+        Self::Toml(err)
+    }
+}
+
+impl From<serde_json::Error> for DbError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// Everything known about a ROM ahead of running it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomProfile {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: Platform,
+    /// Platform quirks with any per-ROM overrides applied
+    pub quirks: Quirks,
+    /// Recommended instructions per 60Hz frame
+    pub ipf: Option<u32>,
+    /// In-game actions and the keypad key that triggers them, sorted by key
+    pub keys: Vec<(String, u8)>,
+}
+
+/// Raw file layout, see `database.toml`
+#[derive(Debug, Deserialize)]
+struct DatabaseFile {
+    #[serde(default)]
+    program: Vec<ProgramEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProgramEntry {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    hash: String,
+    #[serde(default)]
+    platform: Option<String>,
+    #[serde(default)]
+    quirks: QuirksEntry,
+    #[serde(default)]
+    ipf: Option<u32>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuirksEntry {
+    shift: Option<bool>,
+    memory_increment: Option<bool>,
+    jump: Option<bool>,
+    vf_reset: Option<bool>,
+    wrap: Option<bool>,
+}
+
+impl QuirksEntry {
+    fn apply(&self, mut quirks: Quirks) -> Quirks {
+        quirks.shift = self.shift.unwrap_or(quirks.shift);
+        quirks.memory_increment = self.memory_increment.unwrap_or(quirks.memory_increment);
+        quirks.jump = self.jump.unwrap_or(quirks.jump);
+        quirks.vf_reset = self.vf_reset.unwrap_or(quirks.vf_reset);
+        quirks.wrap = self.wrap.unwrap_or(quirks.wrap);
+        quirks
+    }
+}
+
+impl TryFrom<ProgramEntry> for (RomHash, RomProfile) {
+    type Error = DbError;
+
+    fn try_from(entry: ProgramEntry) -> Result<Self, Self::Error> {
+        let hash = entry
+            .hash
+            .parse()
+            .map_err(|err| DbError::InvalidHash(entry.hash.clone(), err))?;
+        let platform = entry
+            .platform
+            .as_deref()
+            .map_or_else(|| Ok(Platform::default()), parse_platform)?;
+        let mut keys = entry
+            .keys
+            .into_iter()
+            .map(|(action, key)| {
+                if key < 16 {
+                    Ok((action, key))
+                } else {
+                    Err(DbError::InvalidKey(action, key))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        keys.sort_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+        let profile = RomProfile {
+            title: entry.title,
+            authors: entry.authors,
+            platform,
+            quirks: entry.quirks.apply(platform.quirks()),
+            ipf: entry.ipf,
+            keys,
+        };
+        Ok((hash, profile))
+    }
+}
+
+/// Accepts both our names and the chip-8-database platform identifiers
+fn parse_platform(name: &str) -> Result<Platform, DbError> {
+    match name {
+        "chip8" | "originalChip8" | "modernChip8" => Ok(Platform::Chip8),
+        "superchip" | "superchip1" | "superchip1.1" => Ok(Platform::SuperChip),
+        "xochip" => Ok(Platform::XoChip),
+        _ => Err(DbError::UnknownPlatform(name.to_string())),
+    }
+}
+
+/// Lookup table of ROM profiles
+#[derive(Debug, Default, Clone)]
+pub struct RomDatabase {
+    profiles: HashMap<RomHash, RomProfile>,
+}
+
+impl RomDatabase {
+    /// Database containing only the bundled profiles
+    ///
+    /// # Panics
+    /// - the bundled database is malformed
+    #[must_use]
+    pub fn bundled() -> Self {
+        let mut db = Self::default();
+        db.extend_from_toml(BUNDLED)
+            .expect("bundled database is valid");
+        db
+    }
+
+    /// Merge a user database, parsed as JSON if the extension is `.json` and TOML otherwise.
+    /// Entries replace existing profiles with the same hash.
+    ///
+    /// # Errors
+    /// - reading the file
+    /// - malformed entries
+    pub fn extend_from_path(&mut self, path: &Path) -> Result<(), DbError> {
+        let contents = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            self.extend_from_json(&contents)
+        } else {
+            self.extend_from_toml(&contents)
+        }
+    }
+
+    /// # Errors
+    /// - malformed TOML or entries
+    pub fn extend_from_toml(&mut self, contents: &str) -> Result<(), DbError> {
+        let file: DatabaseFile = toml::from_str(contents)?;
+        self.extend(file)
+    }
+
+    /// # Errors
+    /// - malformed JSON or entries
+    pub fn extend_from_json(&mut self, contents: &str) -> Result<(), DbError> {
+        let file: DatabaseFile = serde_json::from_str(contents)?;
+        self.extend(file)
+    }
+
+    fn extend(&mut self, file: DatabaseFile) -> Result<(), DbError> {
+        for entry in file.program {
+            let (hash, profile) = entry.try_into()?;
+            self.profiles.insert(hash, profile);
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn lookup(&self, hash: &RomHash) -> Option<&RomProfile> {
+        self.profiles.get(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_roms_are_known() {
+        let db = RomDatabase::bundled();
+        let pong = db
+            .lookup(&RomHash::of(include_bytes!("../../roms/PONG")))
+            .unwrap();
+        assert_eq!(pong.title, "Pong");
+        assert_eq!(pong.platform, Platform::Chip8);
+        assert_eq!(pong.keys[0], ("Player 1 up".to_string(), 0x1));
+        assert!(
+            db.lookup(&RomHash::of(include_bytes!("../../roms/MAZE")))
+                .is_some()
+        );
+        assert!(db.lookup(&RomHash::of(&[0x12, 0x00])).is_none());
+    }
+
+    #[test]
+    fn bundled_profiles_run() {
+        let db = RomDatabase::bundled();
+        for rom in [
+            &include_bytes!("../../roms/PONG")[..],
+            include_bytes!("../../roms/GUESS"),
+            include_bytes!("../../roms/MAZE"),
+        ] {
+            let profile = db.lookup(&RomHash::of(rom)).unwrap();
+            let mut cpu = chip8_interpreter::Chip8Emulator::new();
+            cpu.load_data(rom).unwrap();
+            cpu.set_quirks(profile.quirks);
+            for _ in 0..10000 {
+                if cpu.tick().is_none() {
+                    break;
+                }
+                cpu.tick_timers();
+            }
+        }
+    }
+
+    #[test]
+    fn user_entries_override_bundled() {
+        let mut db = RomDatabase::bundled();
+        db.extend_from_json(
+            r#"{"program": [{
+                "title": "Pong (fast)",
+                "hash": "b232ef880bd6060fb45fa6effed7edf0ae95670e",
+                "platform": "superchip",
+                "quirks": {"vfReset": true},
+                "ipf": 30
+            }]}"#,
+        )
+        .unwrap();
+        let pong = db
+            .lookup(&RomHash::of(include_bytes!("../../roms/PONG")))
+            .unwrap();
+        assert_eq!(pong.title, "Pong (fast)");
+        assert_eq!(pong.ipf, Some(30));
+        assert!(pong.quirks.vf_reset);
+        assert!(pong.quirks.jump);
+    }
+
+    #[test]
+    fn rejects_bad_entries() {
+        let mut db = RomDatabase::default();
+        let err = db.extend_from_toml("[[program]]\ntitle = \"x\"\nhash = \"abc\"");
+        assert!(matches!(err, Err(DbError::InvalidHash(..))));
+        let err = db.extend_from_toml(
+            "[[program]]\ntitle = \"x\"\nhash = \"b232ef880bd6060fb45fa6effed7edf0ae95670e\"\nplatform = \"gameboy\"",
+        );
+        assert!(matches!(err, Err(DbError::UnknownPlatform(_))));
+    }
+}
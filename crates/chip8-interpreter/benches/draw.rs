@@ -0,0 +1,37 @@
+//! Times a loop that does little but draw sprites, the workload the packed display rows target.
+//!
+//! Run with `cargo bench -p chip8-interpreter --bench draw`. It only uses `load_data` and
+//! `run_frame`, so the same file can be dropped into an older checkout to compare.
+//! Packing each display row into a `u64` took it from about 765ms to 405ms on the machine it was
+//! written on.
+
+use std::{hint::black_box, time::Instant};
+
+use chip8_interpreter::Chip8Emulator;
+
+/// Frames run per sample, at the instructions per frame below
+const FRAMES: u32 = 2_000_000;
+const IPF: u32 = 20;
+const SAMPLES: usize = 5;
+
+fn main() {
+    let rom = [
+        0xA0, 0x00, // 200: LD I, 0x000 (the font)
+        0xD0, 0x1F, // 202: DRW V0, V1, 15
+        0x70, 0x03, // 204: ADD V0, 3
+        0x71, 0x01, // 206: ADD V1, 1
+        0x12, 0x02, // 208: JP 202
+    ];
+    let mut best = f64::INFINITY;
+    for _ in 0..SAMPLES {
+        let mut emulator = Chip8Emulator::new();
+        emulator.load_data(&rom).unwrap();
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            black_box(emulator.run_frame(IPF));
+        }
+        best = best.min(start.elapsed().as_secs_f64() * 1000.0);
+        black_box(&emulator);
+    }
+    println!("{FRAMES} frames of {IPF} instructions: best of {SAMPLES} took {best:.0}ms");
+}
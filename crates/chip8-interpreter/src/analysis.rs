@@ -0,0 +1,173 @@
+//! Static exploration of ROM images by recursive descent from the entry point.
+
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::ops::Range;
+
+use crate::{RAM_SIZE, START_ADDR, disasm::Instruction};
+
+/// Reachable code and referenced data found by following control flow from `0x200`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CodeMap {
+    /// Addresses of instructions reachable from the entry point
+    pub instructions: BTreeSet<u16>,
+    /// Targets of `CALL`
+    pub subroutines: BTreeSet<u16>,
+    /// Addresses of `JP V0` instructions whose targets depend on runtime state
+    pub indirect_jumps: BTreeSet<u16>,
+    /// Memory drawn by `DRW` after `LD I`, merged into non-overlapping ranges
+    pub sprites: Vec<Range<u16>>,
+}
+
+/// Memory window a ROM occupies once loaded
+#[must_use]
+pub const fn load_range(rom: &[u8]) -> Range<usize> {
+    START_ADDR as usize..START_ADDR as usize + rom.len()
+}
+
+/// Whether the ROM fits between `0x200` and the end of RAM
+#[must_use]
+pub const fn fits_in_memory(rom: &[u8]) -> bool {
+    load_range(rom).end <= RAM_SIZE
+}
+
+/// Decode the instruction at an address of the loaded ROM. Bytes of an oversized ROM past the
+/// end of RAM are never loaded, so hold no instructions.
+#[must_use]
+pub fn instruction_at(rom: &[u8], addr: u16) -> Option<Instruction> {
+    if usize::from(addr) + 2 > RAM_SIZE {
+        return None;
+    }
+    let offset = (addr as usize).checked_sub(START_ADDR as usize)?;
+    let bytes = rom.get(offset..offset + 2)?;
+    Some(Instruction::decode(u16::from_be_bytes([
+        bytes[0], bytes[1],
+    ])))
+}
+
+/// Addresses control may continue at after executing `instruction` at `addr`.
+/// Indirect jumps have no statically known successors.
+#[must_use]
+pub fn successors(addr: u16, instruction: Instruction) -> Vec<u16> {
+    let next = addr + 2;
+    match instruction {
+        Instruction::Halt | Instruction::Ret | Instruction::JpV0(_) | Instruction::Unknown(_) => {
+            alloc::vec![]
+        }
+        Instruction::Jp(target) => alloc::vec![target],
+        Instruction::Call(target) => alloc::vec![target, next],
+        skip if skip.is_skip() => alloc::vec![next, next + 2],
+        _ => alloc::vec![next],
+    }
+}
+
+/// Walk every path from the entry point, staying inside the loaded ROM
+#[must_use]
+pub fn explore(rom: &[u8]) -> CodeMap {
+    let mut map = CodeMap::default();
+    let mut pending = alloc::vec![START_ADDR];
+    while let Some(addr) = pending.pop() {
+        if map.instructions.contains(&addr) {
+            continue;
+        }
+        let Some(instruction) = instruction_at(rom, addr) else {
+            continue;
+        };
+        map.instructions.insert(addr);
+        match instruction {
+            Instruction::Call(target) => {
+                map.subroutines.insert(target);
+            }
+            Instruction::JpV0(_) => {
+                map.indirect_jumps.insert(addr);
+            }
+            _ => {}
+        }
+        pending.extend(successors(addr, instruction));
+    }
+    map.sprites = sprite_regions(rom, &map.instructions);
+    map
+}
+
+/// Follow each reachable `LD I, addr` along straight-line code to the `DRW`s that use it.
+/// An `LD I` guarded by a skip may or may not run, so both values are kept.
+fn sprite_regions(rom: &[u8], instructions: &BTreeSet<u16>) -> Vec<Range<u16>> {
+    let mut regions: Vec<Range<u16>> = Vec::new();
+    for &addr in instructions {
+        let Some(Instruction::LdI(sprite)) = instruction_at(rom, addr) else {
+            continue;
+        };
+        let mut candidates = alloc::vec![sprite];
+        let mut after_skip = false;
+        let mut cursor = addr + 2;
+        while let Some(instruction) = instruction_at(rom, cursor) {
+            match instruction {
+                Instruction::Drw(_, _, rows) if rows > 0 => {
+                    regions.extend(candidates.iter().map(|&i| i..i + u16::from(rows)));
+                }
+                Instruction::LdI(other) if after_skip => candidates.push(other),
+                // Anything else that moves I or leaves the block ends the trail
+                Instruction::LdI(_)
+                | Instruction::AddI(_)
+                | Instruction::LdF(_)
+                | Instruction::Store(_)
+                | Instruction::Load(_) => break,
+                skip if skip.is_skip() => {}
+                other if successors(cursor, other) != [cursor + 2] => break,
+                _ => {}
+            }
+            after_skip = instruction.is_skip();
+            cursor += 2;
+        }
+    }
+    regions.sort_by_key(|region| region.start);
+    let mut merged: Vec<Range<u16>> = Vec::new();
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => merged.push(region),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explores_calls_skips_and_sprites() {
+        let rom = [
+            0x22, 0x0A, // 0x200: CALL 0x20A
+            0x30, 0x01, // 0x202: SE V0, 1
+            0x12, 0x00, // 0x204: JP 0x200
+            0xB2, 0x00, // 0x206: JP V0, 0x200
+            0x00, 0x00, // 0x208: unreachable
+            0xA2, 0x10, // 0x20A: LD I, 0x210
+            0xD0, 0x13, // 0x20C: DRW V0, V1, 3
+            0x00, 0xEE, // 0x20E: RET
+            0xFF, 0x81, 0xFF, // 0x210: sprite
+        ];
+        let map = explore(&rom);
+        assert_eq!(
+            map.instructions.iter().copied().collect::<Vec<_>>(),
+            [0x200, 0x202, 0x204, 0x206, 0x20A, 0x20C, 0x20E]
+        );
+        assert_eq!(map.subroutines.iter().copied().collect::<Vec<_>>(), [0x20A]);
+        assert_eq!(
+            map.indirect_jumps.iter().copied().collect::<Vec<_>>(),
+            [0x206]
+        );
+        assert_eq!(map.sprites, alloc::vec![0x210..0x213]);
+        assert!(fits_in_memory(&rom));
+    }
+
+    #[test]
+    fn stops_exploring_at_the_end_of_ram() {
+        // LD V0, 0 all the way to 0xFFFF
+        let rom = [0x60, 0x00].repeat((0x10000 - usize::from(START_ADDR)) / 2);
+        let map = explore(&rom);
+        assert_eq!(map.instructions.last(), Some(&0xFFE));
+        assert_eq!(instruction_at(&rom, 0xFFFE), None);
+        assert!(!fits_in_memory(&rom));
+    }
+}
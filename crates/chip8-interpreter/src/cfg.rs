@@ -0,0 +1,431 @@
+//! Control-flow graph of a ROM, split into basic blocks.
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    vec::Vec,
+};
+use core::{fmt::Write, ops::Range};
+
+use crate::{
+    RAM_SIZE,
+    analysis::{self, CodeMap, instruction_at, load_range, successors},
+    disasm::Instruction,
+};
+
+/// Straight-line run of instructions with a single entry and exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: u16,
+    pub instructions: Vec<(u16, Instruction)>,
+}
+
+impl BasicBlock {
+    /// Address just past the last instruction
+    #[must_use]
+    pub fn end(&self) -> u16 {
+        self.instructions
+            .last()
+            .map_or(self.start, |(addr, _)| addr + 2)
+    }
+
+    fn last(&self) -> (u16, Instruction) {
+        self.instructions[self.instructions.len() - 1]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// `JP addr`
+    Jump,
+    /// `CALL addr`
+    Call,
+    /// `RET` back to the instruction after a `CALL` of the enclosing subroutine
+    Return,
+    /// Execution continues into the next block
+    FallThrough,
+    /// A skip instruction whose condition held
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge {
+    /// Start of the source block
+    pub from: u16,
+    /// Start of the destination block
+    pub to: u16,
+    pub kind: EdgeKind,
+}
+
+/// `FX55`/`FX33` whose statically known target overlaps reachable code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfModifyingWrite {
+    /// Address of the writing instruction
+    pub addr: u16,
+    /// Bytes written
+    pub target: Range<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    /// Blocks keyed by start address
+    pub blocks: BTreeMap<u16, BasicBlock>,
+    pub edges: BTreeSet<Edge>,
+    /// `JP V0, addr` instructions, whose targets are computed at runtime
+    pub unresolved: BTreeSet<u16>,
+    /// Parts of the ROM neither reachable as code nor drawn as sprites
+    pub unreachable: Vec<Range<u16>>,
+    pub self_modifying: Vec<SelfModifyingWrite>,
+}
+
+impl Cfg {
+    #[must_use]
+    pub fn build(rom: &[u8]) -> Self {
+        let code = analysis::explore(rom);
+        let blocks = split_blocks(rom, &code);
+        let mut cfg = Self {
+            edges: BTreeSet::new(),
+            unresolved: code.indirect_jumps.clone(),
+            unreachable: unreachable(rom, &code),
+            self_modifying: self_modifying_writes(rom, &code),
+            blocks,
+        };
+        cfg.link();
+        cfg
+    }
+
+    /// Block containing an instruction address
+    #[must_use]
+    pub fn block_at(&self, addr: u16) -> Option<&BasicBlock> {
+        self.blocks
+            .range(..=addr)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| block.instructions.iter().any(|(a, _)| *a == addr))
+    }
+
+    fn link(&mut self) {
+        let mut call_sites: BTreeMap<u16, Vec<u16>> = BTreeMap::new();
+        for block in self.blocks.values() {
+            let (addr, last) = block.last();
+            let next = addr + 2;
+            let targets: Vec<(u16, EdgeKind)> = match last {
+                Instruction::Jp(target) => alloc::vec![(target, EdgeKind::Jump)],
+                Instruction::Call(target) => {
+                    call_sites.entry(target).or_default().push(next);
+                    alloc::vec![(target, EdgeKind::Call)]
+                }
+                skip if skip.is_skip() => {
+                    alloc::vec![(next, EdgeKind::FallThrough), (next + 2, EdgeKind::Skip)]
+                }
+                Instruction::Ret
+                | Instruction::Halt
+                | Instruction::JpV0(_)
+                | Instruction::Unknown(_) => alloc::vec![],
+                _ => alloc::vec![(next, EdgeKind::FallThrough)],
+            };
+            for (to, kind) in targets {
+                if self.blocks.contains_key(&to) {
+                    self.edges.insert(Edge {
+                        from: block.start,
+                        to,
+                        kind,
+                    });
+                }
+            }
+        }
+
+        for (subroutine, return_sites) in call_sites {
+            for ret in self.returns_of(subroutine) {
+                for &site in &return_sites {
+                    if self.blocks.contains_key(&site) {
+                        self.edges.insert(Edge {
+                            from: ret,
+                            to: site,
+                            kind: EdgeKind::Return,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Blocks ending in `RET` reachable from a subroutine entry without leaving it
+    fn returns_of(&self, subroutine: u16) -> Vec<u16> {
+        let mut seen = BTreeSet::new();
+        let mut pending = alloc::vec![subroutine];
+        let mut returns = Vec::new();
+        while let Some(start) = pending.pop() {
+            let Some(block) = self.blocks.get(&start) else {
+                continue;
+            };
+            if !seen.insert(start) {
+                continue;
+            }
+            match block.last() {
+                (_, Instruction::Ret) => returns.push(start),
+                // Nested calls come back to the instruction after them
+                (addr, Instruction::Call(_)) => pending.push(addr + 2),
+                _ => pending.extend(
+                    self.edges
+                        .iter()
+                        .filter(|edge| edge.from == start)
+                        .map(|edge| edge.to),
+                ),
+            }
+        }
+        returns
+    }
+
+    /// Render as a Graphviz digraph
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        // Writing to a String never fails
+        let _ = self.write_dot(&mut dot);
+        dot
+    }
+
+    fn write_dot(&self, dot: &mut String) -> core::fmt::Result {
+        writeln!(dot, "digraph cfg {{")?;
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];")?;
+        for block in self.blocks.values() {
+            write!(dot, "    \"{:#05X}\" [label=\"", block.start)?;
+            for (addr, instruction) in &block.instructions {
+                write!(dot, "{addr:#05X}: {instruction}\\l")?;
+            }
+            write!(dot, "\"")?;
+            let (last, _) = block.last();
+            if self.unresolved.contains(&last) {
+                write!(dot, ", color=red, xlabel=\"unresolved\"")?;
+            } else if self
+                .self_modifying
+                .iter()
+                .any(|write| block.instructions.iter().any(|(a, _)| *a == write.addr))
+            {
+                write!(dot, ", color=orange, xlabel=\"self-modifying\"")?;
+            }
+            writeln!(dot, "];")?;
+        }
+        for region in &self.unreachable {
+            writeln!(
+                dot,
+                "    \"{0:#05X}\" [label=\"{0:#05X}-{1:#05X}: unreachable\", style=dashed, color=gray];",
+                region.start,
+                region.end - 1
+            )?;
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Jump => "label=\"jump\"",
+                EdgeKind::Call => "label=\"call\", color=blue",
+                EdgeKind::Return => "label=\"ret\", style=dashed, color=blue",
+                EdgeKind::FallThrough => "",
+                EdgeKind::Skip => "label=\"skip\"",
+            };
+            writeln!(
+                dot,
+                "    \"{:#05X}\" -> \"{:#05X}\" [{style}];",
+                edge.from, edge.to
+            )?;
+        }
+        writeln!(dot, "}}")
+    }
+}
+
+fn ends_block(addr: u16, instruction: Instruction) -> bool {
+    successors(addr, instruction) != [addr + 2]
+}
+
+fn split_blocks(rom: &[u8], code: &CodeMap) -> BTreeMap<u16, BasicBlock> {
+    let mut leaders = BTreeSet::from([crate::START_ADDR]);
+    for &addr in &code.instructions {
+        let Some(instruction) = instruction_at(rom, addr) else {
+            continue;
+        };
+        if ends_block(addr, instruction) {
+            leaders.extend(successors(addr, instruction));
+            leaders.insert(addr + 2);
+        }
+    }
+
+    let mut blocks: BTreeMap<u16, BasicBlock> = BTreeMap::new();
+    let mut current: Option<BasicBlock> = None;
+    for &addr in &code.instructions {
+        let Some(instruction) = instruction_at(rom, addr) else {
+            continue;
+        };
+        let continues = current
+            .as_ref()
+            .is_some_and(|block| block.end() == addr && !leaders.contains(&addr));
+        if !continues && let Some(block) = current.take() {
+            blocks.insert(block.start, block);
+        }
+        let block = current.get_or_insert_with(|| BasicBlock {
+            start: addr,
+            instructions: Vec::new(),
+        });
+        block.instructions.push((addr, instruction));
+        if ends_block(addr, instruction) {
+            let block = current.take().expect("block was just inserted");
+            blocks.insert(block.start, block);
+        }
+    }
+    if let Some(block) = current {
+        blocks.insert(block.start, block);
+    }
+    blocks
+}
+
+fn unreachable(rom: &[u8], code: &CodeMap) -> Vec<Range<u16>> {
+    // Bytes of an oversized ROM past the end of RAM are never loaded
+    let range = load_range(rom);
+    let range = range.start..range.end.min(RAM_SIZE);
+    let mut covered = alloc::vec![false; range.len()];
+    let mut cover = |addr: Range<u16>| {
+        for byte in addr {
+            if let Some(offset) = (byte as usize).checked_sub(range.start)
+                && let Some(flag) = covered.get_mut(offset)
+            {
+                *flag = true;
+            }
+        }
+    };
+    for &addr in &code.instructions {
+        cover(addr..addr + 2);
+    }
+    for sprite in &code.sprites {
+        cover(sprite.clone());
+    }
+
+    let mut regions: Vec<Range<u16>> = Vec::new();
+    for (offset, _) in covered.iter().enumerate().filter(|(_, c)| !**c) {
+        let addr = (range.start + offset) as u16;
+        match regions.last_mut() {
+            Some(last) if last.end == addr => last.end += 1,
+            _ => regions.push(addr..addr + 1),
+        }
+    }
+    regions
+}
+
+/// Track `LD I, addr` along straight-line code to the `FX55`/`FX33` writes that use it
+fn self_modifying_writes(rom: &[u8], code: &CodeMap) -> Vec<SelfModifyingWrite> {
+    let overlaps_code = |target: &Range<u16>| {
+        code.instructions
+            .iter()
+            .any(|&addr| addr < target.end && target.start < addr + 2)
+    };
+    let mut writes = Vec::new();
+    for &addr in &code.instructions {
+        let Some(Instruction::LdI(mut i)) = instruction_at(rom, addr) else {
+            continue;
+        };
+        let mut cursor = addr + 2;
+        while let Some(instruction) = instruction_at(rom, cursor) {
+            let target = match instruction {
+                Instruction::Store(x) => Some(i..i + u16::from(x) + 1),
+                Instruction::LdB(_) => Some(i..i + 3),
+                _ => None,
+            };
+            if let Some(target) = target
+                && overlaps_code(&target)
+            {
+                writes.push(SelfModifyingWrite {
+                    addr: cursor,
+                    target,
+                });
+            }
+            match instruction {
+                Instruction::LdI(next) => i = next,
+                // Quirky interpreters also move I after a store
+                Instruction::AddI(_)
+                | Instruction::LdF(_)
+                | Instruction::Load(_)
+                | Instruction::Store(_) => break,
+                other if ends_block(cursor, other) => break,
+                _ => {}
+            }
+            cursor += 2;
+        }
+    }
+    writes.sort_by_key(|write| write.addr);
+    writes.dedup();
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_blocks_and_edges() {
+        let rom = [
+            0x22, 0x08, // 0x200: CALL 0x208
+            0x30, 0x01, // 0x202: SE V0, 1
+            0x12, 0x00, // 0x204: JP 0x200
+            0xB2, 0x00, // 0x206: JP V0, 0x200
+            0xA2, 0x02, // 0x208: LD I, 0x202
+            0xF0, 0x33, // 0x20A: LD B, V0
+            0x00, 0xEE, // 0x20C: RET
+            0xAB, 0xCD, // 0x20E: unreachable
+        ];
+        let cfg = Cfg::build(&rom);
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<_>>(),
+            [0x200, 0x202, 0x204, 0x206, 0x208]
+        );
+        let edges: Vec<_> = cfg
+            .edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.kind))
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                (0x200, 0x208, EdgeKind::Call),
+                (0x202, 0x204, EdgeKind::FallThrough),
+                (0x202, 0x206, EdgeKind::Skip),
+                (0x204, 0x200, EdgeKind::Jump),
+                (0x208, 0x202, EdgeKind::Return),
+            ]
+        );
+        assert!(cfg.unresolved.contains(&0x206));
+        assert_eq!(cfg.unreachable, alloc::vec![0x20E..0x210]);
+        assert_eq!(
+            cfg.self_modifying,
+            alloc::vec![SelfModifyingWrite {
+                addr: 0x20A,
+                target: 0x202..0x205
+            }]
+        );
+        assert_eq!(cfg.block_at(0x20A).map(|block| block.start), Some(0x208));
+        assert!(
+            cfg.to_dot()
+                .contains("\"0x202\" -> \"0x206\" [label=\"skip\"];")
+        );
+    }
+
+    #[test]
+    fn stops_at_the_end_of_ram() {
+        // LD V0, 0 all the way to 0xFFFF
+        let rom = [0x60, 0x00].repeat((0x10000 - usize::from(crate::START_ADDR)) / 2);
+        let cfg = Cfg::build(&rom);
+        assert_eq!(cfg.blocks.len(), 1);
+        assert_eq!(cfg.blocks[&0x200].end(), 0x1000);
+        assert!(cfg.unreachable.is_empty());
+    }
+
+    #[test]
+    fn bundled_roms_have_no_unresolved_jumps() {
+        for rom in [
+            &include_bytes!("../../roms/PONG")[..],
+            include_bytes!("../../roms/GUESS"),
+            include_bytes!("../../roms/MAZE"),
+        ] {
+            let cfg = Cfg::build(rom);
+            assert!(cfg.unresolved.is_empty());
+            assert!(cfg.blocks.contains_key(&0x200));
+        }
+    }
+}
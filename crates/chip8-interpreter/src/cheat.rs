@@ -0,0 +1,247 @@
+//! Finding and freezing the bytes that hold game state, like lives or the current level.
+//!
+//! A [`Search`] starts with every byte of RAM and every V register as a candidate, and narrows
+//! them down by comparing values between snapshots taken frames apart. A [`Cheat`] then holds a
+//! location at a fixed value, applied before each frame.
+
+use alloc::vec::Vec;
+use core::{fmt, str::FromStr};
+
+use crate::{Chip8Emulator, NUM_REGS, RAM_SIZE};
+
+/// A byte of machine state a cheat can search or freeze
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+    Memory(u16),
+    /// `V0`-`VF`
+    Register(u8),
+}
+
+impl Location {
+    #[must_use]
+    pub fn read(self, emulator: &Chip8Emulator) -> u8 {
+        match self {
+            Self::Memory(addr) => emulator.memory()[usize::from(addr)],
+            Self::Register(x) => emulator.registers()[usize::from(x)],
+        }
+    }
+
+    pub fn write(self, emulator: &mut Chip8Emulator, value: u8) {
+        match self {
+            Self::Memory(addr) => emulator.memory_mut()[usize::from(addr)] = value,
+            Self::Register(x) => emulator.set_register(usize::from(x), value),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Memory(addr) => write!(f, "{addr:#05X}"),
+            Self::Register(x) => write!(f, "V{x:X}"),
+        }
+    }
+}
+
+/// Returned when a string is neither a register `V0`-`VF` nor a hex address in RAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLocationError;
+
+impl fmt::Display for ParseLocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a register V0-VF or an address 0x000-0xFFF")
+    }
+}
+
+impl core::error::Error for ParseLocationError {}
+
+impl FromStr for Location {
+    type Err = ParseLocationError;
+
+    /// `V3`, or an address in hex with or without `0x`, like `0x2F0`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(digit) = s.strip_prefix(['V', 'v']) {
+            return match u8::from_str_radix(digit, 16) {
+                Ok(x) if digit.len() == 1 => Ok(Self::Register(x)),
+                _ => Err(ParseLocationError),
+            };
+        }
+        let hex = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+        match u16::from_str_radix(hex, 16) {
+            Ok(addr) if usize::from(addr) < RAM_SIZE => Ok(Self::Memory(addr)),
+            _ => Err(ParseLocationError),
+        }
+    }
+}
+
+/// RAM and V registers at one moment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    memory: Vec<u8>,
+    registers: [u8; NUM_REGS],
+}
+
+impl Snapshot {
+    #[must_use]
+    pub fn of(emulator: &Chip8Emulator) -> Self {
+        Self {
+            memory: emulator.memory().to_vec(),
+            registers: *emulator.registers(),
+        }
+    }
+
+    /// Value at `location` when the snapshot was taken
+    #[must_use]
+    pub fn get(&self, location: Location) -> u8 {
+        match location {
+            Location::Memory(addr) => self.memory[usize::from(addr)],
+            Location::Register(x) => self.registers[usize::from(x)],
+        }
+    }
+}
+
+/// How a candidate's value must relate to its value at the last snapshot to stay a candidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    /// Now holds exactly this value, whatever it was before
+    Value(u8),
+}
+
+impl Comparison {
+    #[must_use]
+    pub const fn matches(self, before: u8, after: u8) -> bool {
+        match self {
+            Self::Equal => after == before,
+            Self::Changed => after != before,
+            Self::Increased => after > before,
+            Self::Decreased => after < before,
+            Self::Value(value) => after == value,
+        }
+    }
+}
+
+/// Locations whose values have followed every comparison so far
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Search {
+    snapshot: Snapshot,
+    candidates: Vec<Location>,
+}
+
+impl Search {
+    /// Start with every register and byte of RAM as a candidate, registers first
+    #[must_use]
+    pub fn new(emulator: &Chip8Emulator) -> Self {
+        let registers = (0..NUM_REGS as u8).map(Location::Register);
+        let memory = (0..RAM_SIZE as u16).map(Location::Memory);
+        Self {
+            snapshot: Snapshot::of(emulator),
+            candidates: registers.chain(memory).collect(),
+        }
+    }
+
+    /// Keep the candidates whose value now matches `comparison` against the last snapshot, then
+    /// take a new snapshot to compare with next
+    pub fn narrow(&mut self, emulator: &Chip8Emulator, comparison: Comparison) {
+        let snapshot = Snapshot::of(emulator);
+        self.candidates.retain(|&location| {
+            comparison.matches(self.snapshot.get(location), snapshot.get(location))
+        });
+        self.snapshot = snapshot;
+    }
+
+    #[must_use]
+    pub fn candidates(&self) -> &[Location] {
+        &self.candidates
+    }
+
+    /// Value at the last snapshot
+    #[must_use]
+    pub fn previous(&self, location: Location) -> u8 {
+        self.snapshot.get(location)
+    }
+}
+
+/// A location frozen at a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cheat {
+    pub location: Location,
+    pub value: u8,
+}
+
+impl Cheat {
+    /// Write the value, to be called before every frame
+    pub fn apply(self, emulator: &mut Chip8Emulator) {
+        self.location.write(emulator, self.value);
+    }
+}
+
+/// Apply every cheat in `cheats`
+pub fn apply_all(cheats: &[Cheat], emulator: &mut Chip8Emulator) {
+    for cheat in cheats {
+        cheat.apply(emulator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn narrows_to_a_counter() {
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(&[
+                0x71, 0x01, // 200: ADD V1, 1
+                0xA3, 0x00, // 202: LD I, 0x300
+                0xF1, 0x55, // 204: LD [I], V1
+                0x12, 0x00, // 206: JP 200
+            ])
+            .unwrap();
+        let mut search = Search::new(&emulator);
+        assert_eq!(search.candidates().len(), NUM_REGS + RAM_SIZE);
+
+        emulator.run_frame(4);
+        search.narrow(&emulator, Comparison::Increased);
+        emulator.run_frame(4);
+        search.narrow(&emulator, Comparison::Changed);
+        search.narrow(&emulator, Comparison::Equal);
+        search.narrow(&emulator, Comparison::Value(2));
+        // 0x300 holds V0, which never changes, and 0x301 the copy of V1
+        assert_eq!(
+            search.candidates(),
+            [Location::Register(1), Location::Memory(0x301)]
+        );
+        assert_eq!(search.previous(Location::Register(1)), 2);
+
+        let cheats = [Cheat {
+            location: Location::Register(1),
+            value: 0x40,
+        }];
+        apply_all(&cheats, &mut emulator);
+        emulator.run_frame(4);
+        assert_eq!(emulator.registers()[1], 0x41);
+        assert_eq!(emulator.memory()[0x300..0x302], [0, 0x41]);
+    }
+
+    #[test]
+    fn parses_locations() {
+        assert_eq!("V3".parse(), Ok(Location::Register(3)));
+        assert_eq!("vf".parse(), Ok(Location::Register(0xF)));
+        assert_eq!("0x2F0".parse(), Ok(Location::Memory(0x2F0)));
+        assert_eq!("2f0".parse(), Ok(Location::Memory(0x2F0)));
+        assert_eq!("V10".parse::<Location>(), Err(ParseLocationError));
+        assert_eq!("0x1000".parse::<Location>(), Err(ParseLocationError));
+        assert_eq!(Location::Memory(0x2F0).to_string(), "0x2F0");
+        assert_eq!(Location::Register(0xA).to_string(), "VA");
+    }
+}
@@ -0,0 +1,154 @@
+//! Which bytes of memory a run touched, and how, for testing ROMs.
+//!
+//! [`Coverage`] is a set of [`Chip8Hooks`] marking each address executed, read as data through
+//! `I` or written, see [`Access`].
+
+use alloc::{vec, vec::Vec};
+use core::ops::{BitOr, BitOrAssign};
+
+use crate::{Chip8Emulator, Chip8Hooks, RAM_SIZE};
+
+/// Ways a byte of memory was used, as bit flags
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Access(u8);
+
+impl Access {
+    pub const NONE: Self = Self(0);
+    /// An instruction started here
+    pub const OPCODE: Self = Self(1);
+    /// Part of an executed instruction, either byte
+    pub const EXECUTED: Self = Self(2);
+    /// Read as data, by `Dxyn` or `Fx65`
+    pub const READ: Self = Self(4);
+    /// Written by `Fx33` or `Fx55`
+    pub const WRITTEN: Self = Self(8);
+
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl BitOr for Access {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Access {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    accesses: Vec<Access>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self {
+            accesses: vec![Access::NONE; RAM_SIZE],
+        }
+    }
+}
+
+impl Coverage {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How every address was used, indexed by address
+    #[must_use]
+    pub fn accesses(&self) -> &[Access] {
+        &self.accesses
+    }
+
+    /// How `addr` was used, nothing for addresses outside memory
+    #[must_use]
+    pub fn access(&self, addr: u16) -> Access {
+        self.accesses
+            .get(usize::from(addr))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Addresses used in all the ways in `access`
+    #[must_use]
+    pub fn count(&self, access: Access) -> usize {
+        self.accesses
+            .iter()
+            .filter(|used| used.contains(access))
+            .count()
+    }
+
+    fn mark(&mut self, addr: u16, access: Access) {
+        if let Some(used) = self.accesses.get_mut(usize::from(addr)) {
+            *used |= access;
+        }
+    }
+}
+
+impl Chip8Hooks for Coverage {
+    fn pre_instruction(&mut self, _emulator: &Chip8Emulator, pc: u16, _opcode: u16) {
+        self.mark(pc, Access::OPCODE | Access::EXECUTED);
+        self.mark(pc + 1, Access::EXECUTED);
+    }
+
+    fn memory_read(&mut self, addr: u16, _value: u8) {
+        self.mark(addr, Access::READ);
+    }
+
+    fn memory_write(&mut self, addr: u16, _value: u8) {
+        self.mark(addr, Access::WRITTEN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_executed_read_and_written_bytes() {
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(&[
+                0xA2, 0x0A, // 200: LD I, 0x20A
+                0xF1, 0x65, // 202: LD V1, [I]
+                0xF0, 0x33, // 204: LD B, V0
+                0x12, 0x04, // 206: JP 204
+                0x00, 0x00, // 208: never run
+                0x12, 0x34, // 20A: data
+            ])
+            .unwrap();
+        let mut coverage = Coverage::new();
+        emulator.run_frame_with(6, &mut coverage);
+
+        assert_eq!(coverage.access(0x200), Access::OPCODE | Access::EXECUTED);
+        assert_eq!(coverage.access(0x201), Access::EXECUTED);
+        assert!(coverage.access(0x208).is_empty());
+        // Read into registers, then overwritten with BCD digits
+        assert_eq!(coverage.access(0x20A), Access::READ | Access::WRITTEN);
+        assert_eq!(coverage.access(0x20C), Access::WRITTEN);
+        assert_eq!(coverage.count(Access::OPCODE), 4);
+        assert_eq!(coverage.count(Access::EXECUTED), 8);
+        assert_eq!(coverage.count(Access::READ), 2);
+        assert_eq!(coverage.count(Access::WRITTEN), 3);
+        assert!(Access::READ.contains(Access::NONE));
+        assert_eq!(coverage.access(0xFFFF), Access::NONE);
+    }
+}
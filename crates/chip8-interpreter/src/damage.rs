@@ -0,0 +1,138 @@
+//! Which parts of the display changed, so frontends only redraw when and where they need to.
+
+use core::ops::{BitOr, BitOrAssign};
+
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Rows and columns holding pixels that changed, see [`crate::Chip8Emulator::take_display_damage`]
+///
+/// Every changed pixel lies where a marked row crosses a marked column, though not every such
+/// crossing changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DisplayDamage {
+    // Row 0 in bit 0
+    rows: u32,
+    // Column 0 in the top bit, as in display rows
+    columns: u64,
+}
+
+impl DisplayDamage {
+    /// Nothing changed
+    pub const NONE: Self = Self {
+        rows: 0,
+        columns: 0,
+    };
+    /// The whole display needs drawing
+    pub const FULL: Self = Self {
+        rows: u32::MAX,
+        columns: u64::MAX,
+    };
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.rows == 0
+    }
+
+    /// Bitfield of changed rows, row 0 in bit 0
+    #[must_use]
+    pub const fn rows(self) -> u32 {
+        self.rows
+    }
+
+    /// Bitfield of changed columns, column 0 in the most significant bit like
+    /// [`crate::Chip8Emulator::display_rows`]
+    #[must_use]
+    pub const fn columns(self) -> u64 {
+        self.columns
+    }
+
+    #[must_use]
+    pub const fn has_row(self, y: usize) -> bool {
+        y < SCREEN_HEIGHT && self.rows & 1 << y != 0
+    }
+
+    #[must_use]
+    pub const fn has_column(self, x: usize) -> bool {
+        x < SCREEN_WIDTH && self.columns & 1 << (SCREEN_WIDTH - 1 - x) != 0
+    }
+
+    /// Record a change to the pixel at (`x`, `y`)
+    pub const fn mark(&mut self, x: usize, y: usize) {
+        self.rows |= 1 << y;
+        self.columns |= 1 << (SCREEN_WIDTH - 1 - x);
+    }
+
+    /// Record changes to row `y` in the pixels set in `columns`, laid out like a display row
+    pub const fn mark_row(&mut self, y: usize, columns: u64) {
+        if columns != 0 {
+            self.rows |= 1 << y;
+            self.columns |= columns;
+        }
+    }
+
+    /// Indices of the changed rows, top to bottom
+    pub fn damaged_rows(self) -> impl Iterator<Item = usize> {
+        (0..SCREEN_HEIGHT).filter(move |&y| self.has_row(y))
+    }
+
+    /// Smallest rectangle holding every change, as `(x, y, width, height)`
+    #[must_use]
+    pub const fn bounds(self) -> Option<(usize, usize, usize, usize)> {
+        if self.is_empty() {
+            return None;
+        }
+        let (top, bottom) = (self.rows.trailing_zeros(), 32 - self.rows.leading_zeros());
+        let (left, right) = (
+            self.columns.leading_zeros(),
+            64 - self.columns.trailing_zeros(),
+        );
+        Some((
+            left as usize,
+            top as usize,
+            (right - left) as usize,
+            (bottom - top) as usize,
+        ))
+    }
+}
+
+impl BitOr for DisplayDamage {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self {
+            rows: self.rows | other.rows,
+            columns: self.columns | other.columns,
+        }
+    }
+}
+
+impl BitOrAssign for DisplayDamage {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = *self | other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collects_rows_and_columns() {
+        let mut damage = DisplayDamage::NONE;
+        assert_eq!(damage.bounds(), None);
+        damage.mark(3, 2);
+        damage.mark(10, 5);
+        damage.mark_row(7, 0);
+        assert!(damage.has_row(2) && damage.has_row(5) && !damage.has_row(3));
+        assert!(damage.has_column(10) && !damage.has_column(64));
+        assert_eq!(damage.bounds(), Some((3, 2, 8, 4)));
+        assert_eq!(
+            damage.damaged_rows().collect::<alloc::vec::Vec<_>>(),
+            [2, 5]
+        );
+
+        let full = damage | DisplayDamage::FULL;
+        assert_eq!(full, DisplayDamage::FULL);
+        assert_eq!(full.bounds(), Some((0, 0, SCREEN_WIDTH, SCREEN_HEIGHT)));
+    }
+}
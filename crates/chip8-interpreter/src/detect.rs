@@ -0,0 +1,266 @@
+//! Static heuristics guessing the platform and quirks a ROM was written for.
+//!
+//! Used for ROMs without a database entry. The ROM is scanned as a sequence of aligned opcodes,
+//! so data regions can produce false positives; confidence levels reflect how tell-tale the
+//! evidence is.
+
+use core::fmt;
+
+use crate::{Platform, Quirks};
+
+/// How much weight a guess deserves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Nothing in the ROM points either way, the value is the platform default
+    Low,
+    /// Circumstantial evidence
+    Medium,
+    /// Opcodes or patterns that only make sense one way
+    High,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Guess<T> {
+    pub value: T,
+    pub confidence: Confidence,
+}
+
+impl<T> Guess<T> {
+    const fn new(value: T, confidence: Confidence) -> Self {
+        Self { value, confidence }
+    }
+}
+
+/// Proposed settings for running a ROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Detection {
+    pub platform: Guess<Platform>,
+    pub shift: Guess<bool>,
+    pub memory_increment: Guess<bool>,
+    pub jump: Guess<bool>,
+    pub vf_reset: Guess<bool>,
+    pub wrap: Guess<bool>,
+}
+
+impl Detection {
+    /// Quirks to run the ROM with, regardless of confidence
+    #[must_use]
+    pub const fn quirks(&self) -> Quirks {
+        Quirks {
+            shift: self.shift.value,
+            memory_increment: self.memory_increment.value,
+            jump: self.jump.value,
+            vf_reset: self.vf_reset.value,
+            wrap: self.wrap.value,
+        }
+    }
+}
+
+/// Scan a ROM image for platform specific opcodes and quirk-sensitive patterns
+#[must_use]
+pub fn detect(rom: &[u8]) -> Detection {
+    let platform = detect_platform(rom);
+    let defaults = platform.value.quirks();
+    Detection {
+        platform,
+        shift: detect_shift(rom).unwrap_or_else(|| Guess::new(defaults.shift, Confidence::Low)),
+        memory_increment: detect_memory_increment(rom)
+            .unwrap_or_else(|| Guess::new(defaults.memory_increment, Confidence::Low)),
+        jump: detect_jump(rom).unwrap_or_else(|| Guess::new(defaults.jump, Confidence::Low)),
+        vf_reset: Guess::new(defaults.vf_reset, Confidence::Low),
+        wrap: Guess::new(defaults.wrap, Confidence::Low),
+    }
+}
+
+fn opcodes(rom: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    rom.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+}
+
+const fn nibbles(opcode: u16) -> (u8, u8, u8, u8) {
+    (
+        ((opcode & 0xF000) >> 12) as u8,
+        ((opcode & 0x0F00) >> 8) as u8,
+        ((opcode & 0x00F0) >> 4) as u8,
+        (opcode & 0x000F) as u8,
+    )
+}
+
+fn detect_platform(rom: &[u8]) -> Guess<Platform> {
+    let mut superchip = 0;
+    let mut superchip_strong = false;
+    let mut xochip = 0;
+    let mut xochip_strong = false;
+    for opcode in opcodes(rom) {
+        match nibbles(opcode) {
+            // F000 NNNN - long I load, 00Dn - scroll up
+            (0xF, 0, 0, 0) | (0, 0, 0xD, _) => {
+                xochip += 1;
+                xochip_strong = true;
+            }
+            // FN01 - plane select, 5XY2/5XY3 - register range save/load, F002 - audio pattern
+            (0xF, _, 0, 1) | (5, _, _, 2 | 3) | (0xF, 0, 0, 2) => xochip += 1,
+            // 00FF - hires, 00FE - lores, 00FD - exit
+            (0, 0, 0xF, 0xD..=0xF) => {
+                superchip += 1;
+                superchip_strong = true;
+            }
+            // 00Cn - scroll down, 00FB/00FC - scroll right/left, FX30 - large font, FX75/FX85 - flags
+            (0, 0, 0xC, _) | (0, 0, 0xF, 0xB | 0xC) | (0xF, _, 3, 0) | (0xF, _, 7 | 8, 5) => {
+                superchip += 1;
+            }
+            // DXY0 - 16x16 sprite
+            (0xD, _, _, 0) => superchip += 1,
+            _ => {}
+        }
+    }
+
+    let confidence = |strong: bool, hits: usize| match (strong, hits) {
+        (true, _) | (false, 3..) => Confidence::High,
+        _ => Confidence::Medium,
+    };
+    if xochip > 0 {
+        Guess::new(Platform::XoChip, confidence(xochip_strong, xochip))
+    } else if superchip > 0 {
+        Guess::new(Platform::SuperChip, confidence(superchip_strong, superchip))
+    } else {
+        // No extended opcodes, which is also what a short extended ROM looks like
+        Guess::new(Platform::Chip8, Confidence::Low)
+    }
+}
+
+/// `8XY6`/`8XYE` with distinct registers only make sense if Vy is the shift source.
+/// Loading Vy right before the shift removes any doubt.
+fn detect_shift(rom: &[u8]) -> Option<Guess<bool>> {
+    let mut guess = None;
+    let mut previous = None;
+    for opcode in opcodes(rom) {
+        if let (8, x, y, 6 | 0xE) = nibbles(opcode)
+            && x != y
+        {
+            let loads_vy = previous.is_some_and(|prev| match nibbles(prev) {
+                (6 | 7 | 8 | 0xC, reg, _, _) => reg == y,
+                _ => false,
+            });
+            let confidence = if loads_vy {
+                Confidence::High
+            } else {
+                Confidence::Medium
+            };
+            if guess.is_none_or(|g: Guess<bool>| confidence > g.confidence) {
+                guess = Some(Guess::new(false, confidence));
+            }
+        }
+        previous = Some(opcode);
+    }
+    guess
+}
+
+/// Back to back `FX55`/`FX65` transfers with no `ANNN`/`FX1E`/`FX29` in between rely on I
+/// advancing past the registers that were transferred.
+fn detect_memory_increment(rom: &[u8]) -> Option<Guess<bool>> {
+    let mut pending_transfer = false;
+    let mut guess = None;
+    for opcode in opcodes(rom) {
+        match nibbles(opcode) {
+            (0xF, _, 5 | 6, 5) => {
+                if pending_transfer {
+                    guess = Some(Guess::new(true, Confidence::Medium));
+                }
+                pending_transfer = true;
+            }
+            // I is reassigned, or control flow leaves the straight-line sequence
+            (1 | 2 | 0xA | 0xB, _, _, _) | (0xF, _, 1, 0xE) | (0xF, _, 2, 9) => {
+                pending_transfer = false;
+            }
+            (0, 0, 0xE, 0xE) => pending_transfer = false,
+            _ => {}
+        }
+    }
+    guess
+}
+
+/// `BNNN` right after loading V0 reads as CHIP-8, right after loading the register named by
+/// the top nibble of NNN reads as SUPER-CHIP's `BXNN`.
+fn detect_jump(rom: &[u8]) -> Option<Guess<bool>> {
+    let mut guess = None;
+    let mut previous = None;
+    for opcode in opcodes(rom) {
+        if let (0xB, x, _, _) = nibbles(opcode)
+            && let Some(prev) = previous
+            && let (6 | 7 | 8 | 0xC, reg, _, _) = nibbles(prev)
+        {
+            if reg == 0 {
+                guess = Some(Guess::new(false, Confidence::Medium));
+            } else if reg == x {
+                guess = Some(Guess::new(true, Confidence::Medium));
+            }
+        }
+        previous = Some(opcode);
+    }
+    guess
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_roms_are_chip8() {
+        for rom in [
+            &include_bytes!("../../roms/PONG")[..],
+            include_bytes!("../../roms/GUESS"),
+            include_bytes!("../../roms/MAZE"),
+        ] {
+            assert_eq!(
+                detect(rom).platform,
+                Guess::new(Platform::Chip8, Confidence::Low)
+            );
+        }
+    }
+
+    #[test]
+    fn extended_opcodes_pick_platform() {
+        let superchip = detect(&[0x00, 0xFF, 0xD0, 0x10]);
+        assert_eq!(
+            superchip.platform,
+            Guess::new(Platform::SuperChip, Confidence::High)
+        );
+        assert_eq!(superchip.quirks(), Platform::SuperChip.quirks());
+
+        let xochip = detect(&[0xF0, 0x00, 0x12, 0x34, 0xF1, 0x01]);
+        assert_eq!(
+            xochip.platform,
+            Guess::new(Platform::XoChip, Confidence::High)
+        );
+    }
+
+    #[test]
+    fn quirk_patterns() {
+        let detection = detect(&[
+            0x00, 0xFF, // HIGH
+            0x61, 0x08, // LD V1, 8
+            0x80, 0x16, // SHR V0, V1
+            0xA3, 0x00, // LD I, 0x300
+            0xF1, 0x55, // LD [I], V1
+            0xF1, 0x55, // LD [I], V1
+        ]);
+        assert_eq!(detection.shift, Guess::new(false, Confidence::High));
+        assert_eq!(
+            detection.memory_increment,
+            Guess::new(true, Confidence::Medium)
+        );
+        assert_eq!(detection.jump, Guess::new(true, Confidence::Low));
+    }
+}
@@ -0,0 +1,257 @@
+//! Decoding of opcodes into instructions, displayed using Cowgod's mnemonics.
+
+use core::fmt;
+
+/// A decoded Chip-8 instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    /// 0000 - stops this interpreter
+    Halt,
+    /// 00E0 - CLS
+    Cls,
+    /// 00EE - RET
+    Ret,
+    /// 1nnn - JP addr
+    Jp(u16),
+    /// 2nnn - CALL addr
+    Call(u16),
+    /// 3xkk - SE Vx, byte
+    SeByte(u8, u8),
+    /// 4xkk - SNE Vx, byte
+    SneByte(u8, u8),
+    /// 5xy0 - SE Vx, Vy
+    SeReg(u8, u8),
+    /// 6xkk - LD Vx, byte
+    LdByte(u8, u8),
+    /// 7xkk - ADD Vx, byte
+    AddByte(u8, u8),
+    /// 8xy0 - LD Vx, Vy
+    LdReg(u8, u8),
+    /// 8xy1 - OR Vx, Vy
+    Or(u8, u8),
+    /// 8xy2 - AND Vx, Vy
+    And(u8, u8),
+    /// 8xy3 - XOR Vx, Vy
+    Xor(u8, u8),
+    /// 8xy4 - ADD Vx, Vy
+    AddReg(u8, u8),
+    /// 8xy5 - SUB Vx, Vy
+    Sub(u8, u8),
+    /// 8xy6 - SHR Vx {, Vy}
+    Shr(u8, u8),
+    /// 8xy7 - SUBN Vx, Vy
+    Subn(u8, u8),
+    /// 8xyE - SHL Vx {, Vy}
+    Shl(u8, u8),
+    /// 9xy0 - SNE Vx, Vy
+    SneReg(u8, u8),
+    /// Annn - LD I, addr
+    LdI(u16),
+    /// Bnnn - JP V0, addr
+    JpV0(u16),
+    /// Cxkk - RND Vx, byte
+    Rnd(u8, u8),
+    /// Dxyn - DRW Vx, Vy, nibble
+    Drw(u8, u8, u8),
+    /// `Ex9E` - SKP Vx
+    Skp(u8),
+    /// `ExA1` - SKNP Vx
+    Sknp(u8),
+    /// Fx07 - LD Vx, DT
+    LdVxDt(u8),
+    /// `Fx0A` - LD Vx, K
+    LdVxK(u8),
+    /// Fx15 - LD DT, Vx
+    LdDtVx(u8),
+    /// Fx18 - LD ST, Vx
+    LdStVx(u8),
+    /// `Fx1E` - ADD I, Vx
+    AddI(u8),
+    /// Fx29 - LD F, Vx
+    LdF(u8),
+    /// Fx33 - LD B, Vx
+    LdB(u8),
+    /// `Fx55` - `LD [I], Vx`
+    Store(u8),
+    /// `Fx65` - `LD Vx, [I]`
+    Load(u8),
+    /// Anything this interpreter does not implement
+    Unknown(u16),
+}
+
+impl Instruction {
+    #[must_use]
+    pub const fn decode(opcode: u16) -> Self {
+        let c = ((opcode & 0xF000) >> 12) as u8;
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let d = (opcode & 0x000F) as u8;
+
+        let addr = opcode & 0x0FFF;
+        let byte = (opcode & 0x00FF) as u8;
+
+        match (c, x, y, d) {
+            (0, 0, 0, 0) => Self::Halt,
+            (0, 0, 0xE, 0) => Self::Cls,
+            (0, 0, 0xE, 0xE) => Self::Ret,
+            (1, _, _, _) => Self::Jp(addr),
+            (2, _, _, _) => Self::Call(addr),
+            (3, _, _, _) => Self::SeByte(x, byte),
+            (4, _, _, _) => Self::SneByte(x, byte),
+            (5, _, _, 0) => Self::SeReg(x, y),
+            (6, _, _, _) => Self::LdByte(x, byte),
+            (7, _, _, _) => Self::AddByte(x, byte),
+            (8, _, _, 0) => Self::LdReg(x, y),
+            (8, _, _, 1) => Self::Or(x, y),
+            (8, _, _, 2) => Self::And(x, y),
+            (8, _, _, 3) => Self::Xor(x, y),
+            (8, _, _, 4) => Self::AddReg(x, y),
+            (8, _, _, 5) => Self::Sub(x, y),
+            (8, _, _, 6) => Self::Shr(x, y),
+            (8, _, _, 7) => Self::Subn(x, y),
+            (8, _, _, 0xE) => Self::Shl(x, y),
+            (9, _, _, 0) => Self::SneReg(x, y),
+            (0xA, _, _, _) => Self::LdI(addr),
+            (0xB, _, _, _) => Self::JpV0(addr),
+            (0xC, _, _, _) => Self::Rnd(x, byte),
+            (0xD, _, _, _) => Self::Drw(x, y, d),
+            (0xE, _, 9, 0xE) => Self::Skp(x),
+            (0xE, _, 0xA, 1) => Self::Sknp(x),
+            (0xF, _, 0, 7) => Self::LdVxDt(x),
+            (0xF, _, 0, 0xA) => Self::LdVxK(x),
+            (0xF, _, 1, 5) => Self::LdDtVx(x),
+            (0xF, _, 1, 8) => Self::LdStVx(x),
+            (0xF, _, 1, 0xE) => Self::AddI(x),
+            (0xF, _, 2, 9) => Self::LdF(x),
+            (0xF, _, 3, 3) => Self::LdB(x),
+            (0xF, _, 5, 5) => Self::Store(x),
+            (0xF, _, 6, 5) => Self::Load(x),
+            _ => Self::Unknown(opcode),
+        }
+    }
+
+    /// Opcode pattern from the spec, e.g. `8xy4`, grouping instructions by kind
+    #[must_use]
+    pub const fn pattern(&self) -> &'static str {
+        match self {
+            Self::Halt => "0000",
+            Self::Cls => "00E0",
+            Self::Ret => "00EE",
+            Self::Jp(_) => "1nnn",
+            Self::Call(_) => "2nnn",
+            Self::SeByte(..) => "3xkk",
+            Self::SneByte(..) => "4xkk",
+            Self::SeReg(..) => "5xy0",
+            Self::LdByte(..) => "6xkk",
+            Self::AddByte(..) => "7xkk",
+            Self::LdReg(..) => "8xy0",
+            Self::Or(..) => "8xy1",
+            Self::And(..) => "8xy2",
+            Self::Xor(..) => "8xy3",
+            Self::AddReg(..) => "8xy4",
+            Self::Sub(..) => "8xy5",
+            Self::Shr(..) => "8xy6",
+            Self::Subn(..) => "8xy7",
+            Self::Shl(..) => "8xyE",
+            Self::SneReg(..) => "9xy0",
+            Self::LdI(_) => "Annn",
+            Self::JpV0(_) => "Bnnn",
+            Self::Rnd(..) => "Cxkk",
+            Self::Drw(..) => "Dxyn",
+            Self::Skp(_) => "Ex9E",
+            Self::Sknp(_) => "ExA1",
+            Self::LdVxDt(_) => "Fx07",
+            Self::LdVxK(_) => "Fx0A",
+            Self::LdDtVx(_) => "Fx15",
+            Self::LdStVx(_) => "Fx18",
+            Self::AddI(_) => "Fx1E",
+            Self::LdF(_) => "Fx29",
+            Self::LdB(_) => "Fx33",
+            Self::Store(_) => "Fx55",
+            Self::Load(_) => "Fx65",
+            Self::Unknown(_) => "????",
+        }
+    }
+
+    /// Conditionally skips the following instruction
+    #[must_use]
+    pub const fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Self::SeByte(..)
+                | Self::SneByte(..)
+                | Self::SeReg(..)
+                | Self::SneReg(..)
+                | Self::Skp(_)
+                | Self::Sknp(_)
+        )
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Halt => write!(f, "HALT"),
+            Self::Cls => write!(f, "CLS"),
+            Self::Ret => write!(f, "RET"),
+            Self::Jp(addr) => write!(f, "JP {addr:#05X}"),
+            Self::Call(addr) => write!(f, "CALL {addr:#05X}"),
+            Self::SeByte(x, byte) => write!(f, "SE V{x:X}, {byte:#04X}"),
+            Self::SneByte(x, byte) => write!(f, "SNE V{x:X}, {byte:#04X}"),
+            Self::SeReg(x, y) => write!(f, "SE V{x:X}, V{y:X}"),
+            Self::LdByte(x, byte) => write!(f, "LD V{x:X}, {byte:#04X}"),
+            Self::AddByte(x, byte) => write!(f, "ADD V{x:X}, {byte:#04X}"),
+            Self::LdReg(x, y) => write!(f, "LD V{x:X}, V{y:X}"),
+            Self::Or(x, y) => write!(f, "OR V{x:X}, V{y:X}"),
+            Self::And(x, y) => write!(f, "AND V{x:X}, V{y:X}"),
+            Self::Xor(x, y) => write!(f, "XOR V{x:X}, V{y:X}"),
+            Self::AddReg(x, y) => write!(f, "ADD V{x:X}, V{y:X}"),
+            Self::Sub(x, y) => write!(f, "SUB V{x:X}, V{y:X}"),
+            Self::Shr(x, y) => write!(f, "SHR V{x:X}, V{y:X}"),
+            Self::Subn(x, y) => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Self::Shl(x, y) => write!(f, "SHL V{x:X}, V{y:X}"),
+            Self::SneReg(x, y) => write!(f, "SNE V{x:X}, V{y:X}"),
+            Self::LdI(addr) => write!(f, "LD I, {addr:#05X}"),
+            Self::JpV0(addr) => write!(f, "JP V0, {addr:#05X}"),
+            Self::Rnd(x, byte) => write!(f, "RND V{x:X}, {byte:#04X}"),
+            Self::Drw(x, y, n) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Self::Skp(x) => write!(f, "SKP V{x:X}"),
+            Self::Sknp(x) => write!(f, "SKNP V{x:X}"),
+            Self::LdVxDt(x) => write!(f, "LD V{x:X}, DT"),
+            Self::LdVxK(x) => write!(f, "LD V{x:X}, K"),
+            Self::LdDtVx(x) => write!(f, "LD DT, V{x:X}"),
+            Self::LdStVx(x) => write!(f, "LD ST, V{x:X}"),
+            Self::AddI(x) => write!(f, "ADD I, V{x:X}"),
+            Self::LdF(x) => write!(f, "LD F, V{x:X}"),
+            Self::LdB(x) => write!(f, "LD B, V{x:X}"),
+            Self::Store(x) => write!(f, "LD [I], V{x:X}"),
+            Self::Load(x) => write!(f, "LD V{x:X}, [I]"),
+            Self::Unknown(opcode) => write!(f, "DW {opcode:#06X}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn decodes_and_formats() {
+        let cases = [
+            (0x00E0, "CLS"),
+            (0x2ABC, "CALL 0xABC"),
+            (0x8A1E, "SHL VA, V1"),
+            (0xD125, "DRW V1, V2, 5"),
+            (0xF355, "LD [I], V3"),
+            (0x00FF, "DW 0x00FF"),
+        ];
+        for (opcode, text) in cases {
+            assert_eq!(Instruction::decode(opcode).to_string(), text);
+        }
+        assert!(Instruction::decode(0xE19E).is_skip());
+        assert_eq!(Instruction::decode(0x8124).pattern(), "8xy4");
+    }
+}
@@ -0,0 +1,47 @@
+//! Failures the interpreter reports instead of panicking, for hosts that must not crash.
+
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// The ROM does not fit between `0x200` and the end of RAM
+    RomTooLarge { size: usize },
+    /// `CALL` with all 16 stack levels in use
+    StackOverflow,
+    /// `RET` outside of a subroutine
+    StackUnderflow,
+    /// An opcode this interpreter does not implement
+    UnknownOpcode(u16),
+    /// The program counter ran off the end of RAM
+    ProgramCounterOutOfBounds(u16),
+    /// An instruction accessed memory through I, holding this address, past the end of RAM
+    MemoryOutOfBounds(u16),
+    /// A key index outside the keypad
+    InvalidKey(u8),
+    /// Save state data that is truncated, corrupt or from another version
+    InvalidState,
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::RomTooLarge { size } => write!(f, "ROM of {size} bytes does not fit in memory"),
+            Self::StackOverflow => write!(f, "Stack overflow"),
+            Self::StackUnderflow => write!(f, "Stack underflow"),
+            Self::UnknownOpcode(opcode) => write!(f, "opcode {opcode:04x} is not implemented"),
+            Self::ProgramCounterOutOfBounds(pc) => {
+                write!(f, "program counter {pc:#05X} is outside memory")
+            }
+            Self::MemoryOutOfBounds(i) => {
+                write!(
+                    f,
+                    "memory accessed through I = {i:#05X} runs past the end of RAM"
+                )
+            }
+            Self::InvalidKey(key) => write!(f, "key {key} is outside the keypad"),
+            Self::InvalidState => write!(f, "not a valid chip-8 save state"),
+        }
+    }
+}
+
+impl core::error::Error for Chip8Error {}
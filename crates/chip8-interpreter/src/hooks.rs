@@ -0,0 +1,159 @@
+//! Callbacks from inside instruction execution, so profilers, tracers and debuggers can watch a
+//! program without changes to the interpreter.
+//!
+//! Pass an implementation of [`Chip8Hooks`] to [`Chip8Emulator::try_tick_with`] or
+//! [`Chip8Emulator::try_run_frame_with`]. The methods without hooks run with [`NoHooks`], whose
+//! empty callbacks compile away.
+
+use crate::Chip8Emulator;
+
+/// Which timer an instruction set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timer {
+    /// `Fx15`
+    Delay,
+    /// `Fx18`
+    Sound,
+}
+
+/// Observer of execution. Every callback does nothing unless implemented.
+#[allow(unused_variables)]
+pub trait Chip8Hooks {
+    /// Before the instruction `opcode` at `pc` runs
+    #[inline]
+    fn pre_instruction(&mut self, emulator: &Chip8Emulator, pc: u16, opcode: u16) {}
+
+    /// After the instruction `opcode` from `pc` ran without faulting, unless it was the `0000`
+    /// halt
+    #[inline]
+    fn post_instruction(&mut self, emulator: &Chip8Emulator, pc: u16, opcode: u16) {}
+
+    /// An instruction read `value` from `addr` as data: sprite rows for `Dxyn` and registers for
+    /// `Fx65`. Instruction fetches are reported through [`Self::pre_instruction`] instead.
+    #[inline]
+    fn memory_read(&mut self, addr: u16, value: u8) {}
+
+    /// An instruction stored `value` at `addr`, with `Fx33` or `Fx55`
+    #[inline]
+    fn memory_write(&mut self, addr: u16, value: u8) {}
+
+    /// A sprite `height` rows tall was drawn at (`x`, `y`), already wrapped onto the display.
+    /// `collision` is whether it erased any pixels.
+    #[inline]
+    fn draw(&mut self, x: u8, y: u8, height: u8, collision: bool) {}
+
+    /// The display was cleared with `00E0`
+    #[inline]
+    fn clear(&mut self) {}
+
+    /// A timer was set to `value`
+    #[inline]
+    fn timer_write(&mut self, timer: Timer, value: u8) {}
+
+    /// `Fx0A` ran, finding `key` pressed, or `None` if it is still waiting and will run again
+    #[inline]
+    fn key_wait(&mut self, key: Option<u8>) {}
+
+    /// A frame run with [`Chip8Emulator::try_run_frame_with`] finished, timers included
+    #[inline]
+    fn end_frame(&mut self, emulator: &Chip8Emulator) {}
+}
+
+/// Hooks that do nothing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoHooks;
+
+impl Chip8Hooks for NoHooks {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Trace {
+        pcs: Vec<u16>,
+        reads: Vec<(u16, u8)>,
+        writes: Vec<(u16, u8)>,
+        draws: Vec<(u8, u8, u8, bool)>,
+        clears: usize,
+        timers: Vec<(Timer, u8)>,
+        waits: Vec<Option<u8>>,
+        posts: usize,
+        frames: usize,
+    }
+
+    impl Chip8Hooks for Trace {
+        fn pre_instruction(&mut self, emulator: &Chip8Emulator, pc: u16, _opcode: u16) {
+            assert_eq!(emulator.program_counter(), pc);
+            self.pcs.push(pc);
+        }
+        fn post_instruction(&mut self, _emulator: &Chip8Emulator, _pc: u16, _opcode: u16) {
+            self.posts += 1;
+        }
+        fn memory_read(&mut self, addr: u16, value: u8) {
+            self.reads.push((addr, value));
+        }
+        fn memory_write(&mut self, addr: u16, value: u8) {
+            self.writes.push((addr, value));
+        }
+        fn draw(&mut self, x: u8, y: u8, height: u8, collision: bool) {
+            self.draws.push((x, y, height, collision));
+        }
+        fn clear(&mut self) {
+            self.clears += 1;
+        }
+        fn timer_write(&mut self, timer: Timer, value: u8) {
+            self.timers.push((timer, value));
+        }
+        fn key_wait(&mut self, key: Option<u8>) {
+            self.waits.push(key);
+        }
+        fn end_frame(&mut self, _emulator: &Chip8Emulator) {
+            self.frames += 1;
+        }
+    }
+
+    #[test]
+    fn reports_execution() {
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(&[
+                0x00, 0xE0, // CLS
+                0x60, 0x42, // LD V0, 0x42
+                0xA3, 0x00, // LD I, 0x300
+                0xF0, 0x55, // LD [I], V0
+                0xA0, 0x05, // LD I, font 1
+                0xD0, 0x12, // DRW V0, V1, 2
+                0xF0, 0x15, // LD DT, V0
+                0xF1, 0x0A, // LD V1, K
+                0x00, 0x00, // halt
+            ])
+            .unwrap();
+        let mut trace = Trace::default();
+        assert_eq!(emulator.try_run_frame_with(9, &mut trace), Ok(Some(())));
+        assert_eq!(
+            trace.pcs,
+            [
+                0x200, 0x202, 0x204, 0x206, 0x208, 0x20A, 0x20C, 0x20E, 0x20E
+            ]
+        );
+        assert_eq!(trace.posts, 9);
+        assert_eq!(trace.clears, 1);
+        assert_eq!(trace.writes, [(0x300, 0x42)]);
+        assert_eq!(trace.reads, [(0x005, 0x20), (0x006, 0x60)]);
+        assert_eq!(trace.draws, [(0x02, 0, 2, false)]);
+        assert_eq!(trace.timers, [(Timer::Delay, 0x42)]);
+        assert_eq!(trace.waits, [None, None]);
+        assert_eq!(trace.frames, 1);
+
+        emulator.keypress(7, true);
+        let mut trace = Trace::default();
+        assert_eq!(emulator.try_run_frame_with(2, &mut trace), Ok(None));
+        assert_eq!(trace.waits, vec![Some(7)]);
+        // The halt is seen but does not complete
+        assert_eq!(trace.pcs, [0x20E, 0x210]);
+        assert_eq!(trace.posts, 1);
+    }
+}
@@ -0,0 +1,107 @@
+//! Reading and changing machine state from outside, for debuggers and other tools.
+
+use crate::{Chip8Emulator, NUM_REGS};
+
+impl Chip8Emulator {
+    /// V0-VF
+    #[must_use]
+    pub const fn registers(&self) -> &[u8; NUM_REGS] {
+        &self.v_registers
+    }
+
+    /// Set register `Vx`
+    ///
+    /// # Panics
+    /// If `x` is not 0-15
+    pub const fn set_register(&mut self, x: usize, value: u8) {
+        self.v_registers[x] = value;
+    }
+
+    #[must_use]
+    pub const fn i_register(&self) -> u16 {
+        self.i_register
+    }
+
+    pub const fn set_i_register(&mut self, value: u16) {
+        self.i_register = value;
+    }
+
+    /// Address of the next instruction to execute
+    #[must_use]
+    pub const fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    /// Jump to `addr`. An address outside memory faults on the next instruction.
+    pub const fn set_program_counter(&mut self, addr: u16) {
+        self.program_counter = addr;
+    }
+
+    /// Return addresses of the subroutines being run, outermost first
+    #[must_use]
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer]
+    }
+
+    #[must_use]
+    pub const fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub const fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    #[must_use]
+    pub const fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub const fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    /// All 4 KiB of RAM, including the font at `0x000`
+    #[must_use]
+    pub const fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// RAM for patching code or data in place
+    pub const fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_can_be_read_and_changed() {
+        let mut cpu = Chip8Emulator::new();
+        // CALL 0x204; HALT; LD V3, 0x42
+        cpu.load_data(&[0x22, 0x04, 0x00, 0x00, 0x63, 0x42])
+            .unwrap();
+        cpu.tick();
+        cpu.tick();
+        assert_eq!(cpu.stack(), [0x202]);
+        assert_eq!(cpu.program_counter(), 0x206);
+        assert_eq!(cpu.registers()[3], 0x42);
+
+        // Patch in `LD V3, 0x07` and run it again
+        cpu.memory_mut()[0x205] = 0x07;
+        cpu.set_program_counter(0x204);
+        cpu.set_register(0xF, 1);
+        cpu.set_i_register(0x300);
+        cpu.set_delay_timer(2);
+        cpu.set_sound_timer(1);
+        cpu.tick();
+        cpu.tick_timers();
+        assert_eq!(cpu.registers()[3], 0x07);
+        assert_eq!(cpu.registers()[0xF], 1);
+        assert_eq!(cpu.i_register(), 0x300);
+        assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (1, 0));
+        assert_eq!(cpu.memory()[0x204..0x206], [0x63, 0x07]);
+    }
+}
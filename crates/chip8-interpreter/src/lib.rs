@@ -1,15 +1,51 @@
 //! Implementation of Chip-8 Interpreter based on spec:
 //! <http://devernay.free.fr/hacks/chip8/C8TECH10.HTM>
 
-#![no_std]
+#![cfg_attr(not(any(feature = "rustler", feature = "wasm")), no_std)]
 #![allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
 
-use core::fmt;
+extern crate alloc;
+
+pub mod analysis;
+pub mod cfg;
+pub mod cheat;
+pub mod coverage;
+mod damage;
+pub mod detect;
+pub mod disasm;
+mod error;
+mod hooks;
+mod inspect;
+pub mod movie;
+#[cfg(feature = "rustler")]
+mod nif;
+pub mod profile;
+mod quirks;
+pub mod render;
+mod rom;
+mod state;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+use core::{fmt, ops::Range};
+
+pub use damage::DisplayDamage;
+pub use error::Chip8Error;
+pub use hooks::{Chip8Hooks, NoHooks, Timer};
+pub use quirks::{Platform, Quirks};
+pub use rom::{ParseRomHashError, RomHash};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmChip8;
 
 const RAM_SIZE: usize = 4096;
 // The original implementation of the Chip-8 language used a 64x32-pixel monochrome display with this format:
 pub const SCREEN_HEIGHT: usize = 32;
 pub const SCREEN_WIDTH: usize = 64;
+/// Bytes in the display packed a bit per pixel, see [`Chip8Emulator::display_bytes`]
+pub const DISPLAY_BYTES: usize = SCREEN_WIDTH * SCREEN_HEIGHT / 8;
+
+// Display rows are packed into a `u64` each
+const _: () = assert!(SCREEN_WIDTH == u64::BITS as usize);
 
 // 16 general purpose 8-bit registers, usually referred to as Vx, where x is a hexadecimal digit (0 through F).
 const NUM_REGS: usize = 16;
@@ -59,10 +95,14 @@ pub struct Chip8Emulator {
     //  The stack is an array of 16 16-bit values, used to store the address that the interpreter shoud return to when
     // finished with a subroutine. Chip-8 allows for up to 16 levels of nested subroutines.
     stack: [u16; STACK_SIZE],
-    // Tracks what pixels are on/off
-    display: [bool; SCREEN_HEIGHT * SCREEN_WIDTH],
+    // Tracks what pixels are on/off, a row per `u64` with the leftmost pixel in the top bit
+    display: [u64; SCREEN_HEIGHT],
+    // Where the display changed since `take_display_damage`
+    damage: DisplayDamage,
     // Tracks which keys are pressed
     keyboard: [bool; NUM_KEYS],
+    // Bitfield of keys read by Ex9E, ExA1 and Fx0A since `take_polled_keys`
+    polled_keys: u16,
     //  The delay timer is active whenever the delay timer register (DT) is non-zero.
     // This timer does nothing more than subtract 1 from the value of DT at a rate of 60Hz. When DT reaches 0, it deactivates.
     delay_timer: u8,
@@ -70,10 +110,18 @@ pub struct Chip8Emulator {
     // This timer also decrements at a rate of 60Hz, however, as long as ST's value is greater than zero, the Chip-8 buzzer will sound.
     // When ST reaches zero, the sound timer deactivates.
     sound_timer: u8,
+    // Interpreter behaviour toggles for the loaded ROM
+    quirks: Quirks,
+    // SHA-1 of the data passed to `load_data`
+    rom_hash: RomHash,
+    // Source of `Cxkk` random numbers, seeded so runs can be reproduced
+    rng: fastrand::Rng,
+    seed: u64,
 }
 
 impl Default for Chip8Emulator {
     fn default() -> Self {
+        let seed = fastrand::u64(..);
         Self {
             v_registers: Default::default(),
             i_register: Default::default(),
@@ -81,29 +129,29 @@ impl Default for Chip8Emulator {
             memory: [0; 4096],
             stack_pointer: Default::default(),
             stack: Default::default(),
-            display: [false; SCREEN_HEIGHT * SCREEN_WIDTH],
+            display: [0; SCREEN_HEIGHT],
+            // Nothing has been drawn yet
+            damage: DisplayDamage::FULL,
             keyboard: Default::default(),
+            polled_keys: 0,
             delay_timer: Default::default(),
             sound_timer: Default::default(),
+            quirks: Quirks::default(),
+            rom_hash: RomHash::default(),
+            rng: fastrand::Rng::with_seed(seed),
+            seed,
         }
     }
 }
 
-#[cfg(feature = "rustler")]
-impl rustler::Resource for Chip8Emulator {}
-
-#[cfg(feature = "rustler")]
-#[rustler::resource_impl]
-impl Chip8Emulator {}
-
 impl fmt::Display for Chip8Emulator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for line in self.display.as_slice().chunks(SCREEN_WIDTH) {
-            for &segment in line {
-                let symbol = if segment == true { '◻' } else { '◼' };
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let symbol = if self.pixel(x, y) { '◻' } else { '◼' };
                 write!(f, "{}", symbol)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -117,34 +165,151 @@ impl Chip8Emulator {
         emu
     }
 
-    pub fn load_data(&mut self, data: &[u8]) {
+    /// Copy a ROM into memory at `0x200`
+    ///
+    /// # Errors
+    /// `RomTooLarge` if the ROM runs past the end of RAM, leaving memory untouched
+    pub fn load_data(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
         let start = START_ADDR as usize;
         let end = (START_ADDR as usize) + data.len();
+        if end > RAM_SIZE {
+            return Err(Chip8Error::RomTooLarge { size: data.len() });
+        }
         self.memory[start..end].copy_from_slice(data);
+        self.rom_hash = RomHash::of(data);
+        Ok(())
+    }
+
+    /// Hash of the ROM most recently passed to `load_data`
+    #[must_use]
+    pub const fn rom_hash(&self) -> RomHash {
+        self.rom_hash
+    }
+
+    #[must_use]
+    pub const fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Change instruction semantics to suit the loaded ROM
+    pub const fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Seed the random number generator was last (re)started from
+    #[must_use]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restart the random number generator so `Cxkk` results can be reproduced
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng.seed(seed);
+    }
+
+    /// FNV-1a hash of all machine state, used to detect diverging runs
+    #[must_use]
+    pub fn state_hash(&self) -> u64 {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        let mut hash = OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        write(&self.v_registers);
+        write(&self.i_register.to_be_bytes());
+        write(&self.program_counter.to_be_bytes());
+        write(&self.memory);
+        write(&[self.stack_pointer as u8]);
+        for addr in self.stack {
+            write(&addr.to_be_bytes());
+        }
+        // A byte per pixel, as before the display was packed, so recorded hashes still match
+        for pixel in self.get_display() {
+            write(&[u8::from(pixel)]);
+        }
+        for key in self.keyboard {
+            write(&[u8::from(key)]);
+        }
+        write(&[self.delay_timer, self.sound_timer]);
+        write(&self.rng.get_seed().to_be_bytes());
+        hash
     }
 
     fn load_data_range(&mut self, data: &[u8], start_idx: usize) {
         self.memory[start_idx..start_idx + data.len()].copy_from_slice(data);
     }
 
-    /// Return the state of the display
+    /// The `len` bytes of memory starting at I, if they are all inside RAM
+    fn i_range(&self, len: usize) -> Result<Range<usize>, Chip8Error> {
+        let start = usize::from(self.i_register);
+        if start + len > RAM_SIZE {
+            return Err(Chip8Error::MemoryOutOfBounds(self.i_register));
+        }
+        Ok(start..start + len)
+    }
+
+    /// Return the state of the display, pixel by pixel in row-major order
+    #[must_use]
+    pub fn get_display(&self) -> impl ExactSizeIterator<Item = bool> + '_ {
+        (0..SCREEN_WIDTH * SCREEN_HEIGHT)
+            .map(|idx| self.pixel(idx % SCREEN_WIDTH, idx / SCREEN_WIDTH))
+    }
+
+    /// Rows of the display, the leftmost pixel in the most significant bit
     #[must_use]
-    pub const fn get_display(&self) -> &[bool] {
+    pub const fn display_rows(&self) -> &[u64; SCREEN_HEIGHT] {
         &self.display
     }
 
+    /// Whether the pixel at (`x`, `y`) is lit
+    ///
+    /// # Panics
+    /// If `y` is off the display
+    #[must_use]
+    pub const fn pixel(&self, x: usize, y: usize) -> bool {
+        x < SCREEN_WIDTH && self.display[y] & 1 << (SCREEN_WIDTH - 1 - x) != 0
+    }
+
+    /// The display a bit per pixel, row-major with the leftmost pixel in the most significant bit
+    /// of each byte. An eighth of the size of [`Self::get_display`], for sending elsewhere.
+    #[must_use]
+    pub fn display_bytes(&self) -> [u8; DISPLAY_BYTES] {
+        let mut bytes = [0; DISPLAY_BYTES];
+        for (chunk, row) in bytes.chunks_exact_mut(8).zip(self.display) {
+            chunk.copy_from_slice(&row.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Rows and columns of the display changed since the last call, so frontends can skip
+    /// redrawing unchanged frames or limit drawing to what changed
+    pub const fn take_display_damage(&mut self) -> DisplayDamage {
+        core::mem::replace(&mut self.damage, DisplayDamage::NONE)
+    }
+
     /// Press a key 0-15
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
         debug_assert!(idx < NUM_KEYS, "{idx} is outside bounds");
         self.keyboard[idx] = pressed;
     }
 
-    const fn read_opcode(&mut self) -> u16 {
+    /// Keys the program has checked since the last call, as a bitfield with key 0 in bit 0.
+    /// A program waiting on `Fx0A` is polling every key.
+    pub const fn take_polled_keys(&mut self) -> u16 {
+        let polled = self.polled_keys;
+        self.polled_keys = 0;
+        polled
+    }
+
+    const fn fetch_opcode(&self) -> u16 {
         let op_byte_1 = self.memory[self.program_counter as usize] as u16;
         let op_byte_2 = self.memory[(self.program_counter + 1) as usize] as u16;
-        let op_code = (op_byte_1 << 8) | op_byte_2;
-        self.program_counter += OPCODE_SIZE;
-        op_code
+        (op_byte_1 << 8) | op_byte_2
     }
 
     pub const fn tick_timers(&mut self) {
@@ -159,8 +324,87 @@ impl Chip8Emulator {
         }
     }
 
+    /// Run one 60Hz frame: `ipf` instructions followed by a timer tick.
+    /// Returns `None` once the program has halted, after which frames only tick the timers.
+    ///
+    /// # Panics
+    /// On the errors reported by [`Self::try_run_frame`]
+    pub fn run_frame(&mut self, ipf: u32) -> Option<()> {
+        self.run_frame_with(ipf, &mut NoHooks)
+    }
+
+    /// [`Self::run_frame`] reporting what happens to `hooks`
+    ///
+    /// # Panics
+    /// On the errors reported by [`Self::try_run_frame`]
+    pub fn run_frame_with<H: Chip8Hooks>(&mut self, ipf: u32, hooks: &mut H) -> Option<()> {
+        self.try_run_frame_with(ipf, hooks)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// [`Self::run_frame`] reporting faults in the program instead of panicking
+    ///
+    /// # Errors
+    /// See [`Self::try_tick`]
+    pub fn try_run_frame(&mut self, ipf: u32) -> Result<Option<()>, Chip8Error> {
+        self.try_run_frame_with(ipf, &mut NoHooks)
+    }
+
+    /// [`Self::try_run_frame`] reporting what happens to `hooks`
+    ///
+    /// # Errors
+    /// See [`Self::try_tick`]
+    pub fn try_run_frame_with<H: Chip8Hooks>(
+        &mut self,
+        ipf: u32,
+        hooks: &mut H,
+    ) -> Result<Option<()>, Chip8Error> {
+        let mut running = Some(());
+        for _ in 0..ipf {
+            if self.try_tick_with(hooks)?.is_none() {
+                // Stay on the halt instruction rather than running into whatever follows it
+                self.program_counter -= OPCODE_SIZE;
+                running = None;
+                break;
+            }
+        }
+        self.tick_timers();
+        hooks.end_frame(self);
+        Ok(running)
+    }
+
+    /// Execute one instruction, returning `None` on the `0000` halt instruction
+    ///
+    /// # Panics
+    /// On the errors reported by [`Self::try_tick`]
     pub fn tick(&mut self) -> Option<()> {
-        let opcode = self.read_opcode();
+        self.try_tick().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// [`Self::tick`] reporting faults in the program instead of panicking
+    ///
+    /// # Errors
+    /// Unknown opcodes, stack overflow or underflow, a program counter outside memory, memory
+    /// accessed through I past the end of RAM, and keys outside the keypad
+    pub fn try_tick(&mut self) -> Result<Option<()>, Chip8Error> {
+        self.try_tick_with(&mut NoHooks)
+    }
+
+    /// [`Self::try_tick`] reporting what happens to `hooks`
+    ///
+    /// # Errors
+    /// See [`Self::try_tick`]
+    pub fn try_tick_with<H: Chip8Hooks>(
+        &mut self,
+        hooks: &mut H,
+    ) -> Result<Option<()>, Chip8Error> {
+        if self.program_counter as usize + 1 >= RAM_SIZE {
+            return Err(Chip8Error::ProgramCounterOutOfBounds(self.program_counter));
+        }
+        let pc = self.program_counter;
+        let opcode = self.fetch_opcode();
+        hooks.pre_instruction(self, pc, opcode);
+        self.program_counter += OPCODE_SIZE;
 
         /*
         nnn or addr - A 12-bit value, the lowest 12 bits of the instruction
@@ -180,12 +424,12 @@ impl Chip8Emulator {
 
         match (c, x, y, d) {
             (0, 0, 0, 0) => {
-                return None;
+                return Ok(None);
             }
-            (0, 0, 0xE, 0) => self.cls(),
-            (0, 0, 0xE, 0xE) => self.ret(),
+            (0, 0, 0xE, 0) => self.cls(hooks),
+            (0, 0, 0xE, 0xE) => self.ret()?,
             (1, _, _, _) => self.jmp(addr),
-            (2, _, _, _) => self.call(addr),
+            (2, _, _, _) => self.call(addr)?,
             (3, _, _, _) => self.skip_val_eq(x, byte),
             (4, _, _, _) => self.skip_val_not_eq(x, byte),
             (5, _, _, _) => self.skip_registers_eq(x, y),
@@ -197,28 +441,29 @@ impl Chip8Emulator {
             (8, _, _, 3) => self.xor(x, y),
             (8, _, _, 4) => self.add_xy(x, y),
             (8, _, _, 5) => self.sub_xy(x, y),
-            (8, _, _, 6) => self.shift_right(x),
+            (8, _, _, 6) => self.shift_right(x, y),
             (8, _, _, 7) => self.subn(x, y),
-            (8, _, _, 0xE) => self.shift_left(x),
+            (8, _, _, 0xE) => self.shift_left(x, y),
             (9, _, _, 0) => self.skip_registers_ne(x, y),
             (0xA, _, _, _) => self.load_i_reg(addr),
-            (0xB, _, _, _) => self.jump_from(addr),
+            (0xB, _, _, _) => self.jump_from(x, addr),
             (0xC, _, _, _) => self.rand(x, byte),
-            (0xD, _, _, _) => self.display(x, y, d),
-            (0xE, _, 9, 0xE) => self.skip_if_key(x),
-            (0xE, _, 0xA, 1) => self.skip_not_key(x),
+            (0xD, _, _, _) => self.display(hooks, x, y, d)?,
+            (0xE, _, 9, 0xE) => self.skip_if_key(x)?,
+            (0xE, _, 0xA, 1) => self.skip_not_key(x)?,
             (0xF, _, 0, 7) => self.set_register_to_delay(x),
-            (0xF, _, 0, 0xA) => self.wait_timer(x),
-            (0xF, _, 1, 5) => self.set_timer(x),
-            (0xF, _, 1, 8) => self.set_sound_timer(x),
+            (0xF, _, 0, 0xA) => self.wait_timer(hooks, x),
+            (0xF, _, 1, 5) => self.set_timer(hooks, x),
+            (0xF, _, 1, 8) => self.set_sound_from_register(hooks, x),
             (0xF, _, 1, 0xE) => self.add_to_i_register(x),
             (0xF, _, 2, 9) => self.set_i_to_font_addr(x),
-            (0xF, _, 3, 3) => self.store_bcd_encoding(x),
-            (0xF, _, 5, 5) => self.store_registers_at_i(x),
-            (0xF, _, 6, 5) => self.load_registers_from_i_addr(x),
-            _ => todo!("opcode {:04x} is not implemented", opcode),
+            (0xF, _, 3, 3) => self.store_bcd_encoding(hooks, x)?,
+            (0xF, _, 5, 5) => self.store_registers_at_i(hooks, x)?,
+            (0xF, _, 6, 5) => self.load_registers_from_i_addr(hooks, x)?,
+            _ => return Err(Chip8Error::UnknownOpcode(opcode)),
         }
-        Some(())
+        hooks.post_instruction(self, pc, opcode);
+        Ok(Some(()))
     }
 
     const fn skip_val_eq(&mut self, x: u8, byte: u8) {
@@ -274,18 +519,28 @@ impl Chip8Emulator {
         // Set Vx = Vx OR Vy.
         // Performs a bitwise OR on the values of Vx and Vy, then stores the result in Vx.
         self.v_registers[x as usize] |= self.v_registers[y as usize];
+        self.reset_vf();
     }
     const fn and(&mut self, x: u8, y: u8) {
         // 8xy2 - AND Vx, Vy
         // Set Vx = Vx AND Vy.
         // Performs a bitwise AND on the values of Vx and Vy, then stores the result in Vx.
         self.v_registers[x as usize] &= self.v_registers[y as usize];
+        self.reset_vf();
     }
     const fn xor(&mut self, x: u8, y: u8) {
         //8xy3 - XOR Vx, Vy
         // Set Vx = Vx XOR Vy.
         // Performs a bitwise exclusive OR on the values of Vx and Vy, then stores the result in Vx.
         self.v_registers[x as usize] ^= self.v_registers[y as usize];
+        self.reset_vf();
+    }
+
+    const fn reset_vf(&mut self) {
+        // The COSMAC VIP logic routines clobber VF as a side effect
+        if self.quirks.vf_reset {
+            self.v_registers[0xF] = 0;
+        }
     }
     const fn sub_xy(&mut self, x: u8, y: u8) {
         //8xy5 - SUB Vx, Vy
@@ -302,10 +557,13 @@ impl Chip8Emulator {
         }
     }
 
-    const fn shift_right(&mut self, x: u8) {
+    const fn shift_right(&mut self, x: u8, y: u8) {
         // 8xy6 - SHR Vx {, Vy}
         // Set Vx = Vx SHR 1.
         // If the least-significant bit of Vx is 1, then VF is set to 1, otherwise 0. Then Vx is divided by 2.
+        if !self.quirks.shift {
+            self.v_registers[x as usize] = self.v_registers[y as usize];
+        }
         let lsb = self.v_registers[x as usize] & 1;
         self.v_registers[x as usize] >>= 1;
         self.v_registers[0xF] = lsb;
@@ -326,10 +584,13 @@ impl Chip8Emulator {
         }
     }
 
-    const fn shift_left(&mut self, x: u8) {
+    const fn shift_left(&mut self, x: u8, y: u8) {
         // 8xyE - SHL Vx {, Vy}
         // Set Vx = Vx SHL 1.
         //If the most-significant bit of Vx is 1, then VF is set to 1, otherwise to 0. Then Vx is multiplied by 2.
+        if !self.quirks.shift {
+            self.v_registers[x as usize] = self.v_registers[y as usize];
+        }
         let msb = (self.v_registers[x as usize] >> 7) & 1;
         self.v_registers[x as usize] <<= 1;
         self.v_registers[0xF] = msb;
@@ -344,10 +605,14 @@ impl Chip8Emulator {
         }
     }
 
-    const fn cls(&mut self) {
+    fn cls(&mut self, hooks: &mut impl Chip8Hooks) {
         // 00E0 - CLS
         // Clear the display.
-        self.display = [false; SCREEN_HEIGHT * SCREEN_WIDTH];
+        for (y, row) in self.display.iter_mut().enumerate() {
+            self.damage.mark_row(y, *row);
+            *row = 0;
+        }
+        hooks.clear();
     }
 
     const fn jmp(&mut self, addr: u16) {
@@ -357,25 +622,31 @@ impl Chip8Emulator {
         self.program_counter = addr;
     }
 
-    fn call(&mut self, addr: u16) {
+    const fn call(&mut self, addr: u16) -> Result<(), Chip8Error> {
         // 2nnn - CALL addr
         // Call subroutine at nnn.
         // The interpreter increments the stack pointer, then puts the current PC on the top of the stack. The PC is then set to nnn.
-        assert!(self.stack_pointer < self.stack.len(), "Stack overflow");
+        if self.stack_pointer >= self.stack.len() {
+            return Err(Chip8Error::StackOverflow);
+        }
 
         self.stack[self.stack_pointer] = self.program_counter;
         self.stack_pointer += 1;
         self.program_counter = addr;
+        Ok(())
     }
 
-    const fn ret(&mut self) {
+    const fn ret(&mut self) -> Result<(), Chip8Error> {
         // 00EE - RET
         // Return from a subroutine.
         // The interpreter sets the program counter to the address at the top of the stack, then subtracts 1 from the stack pointer.
-        assert!(self.stack_pointer != 0, "Stack underflow");
+        if self.stack_pointer == 0 {
+            return Err(Chip8Error::StackUnderflow);
+        }
 
         self.stack_pointer -= 1;
         self.program_counter = self.stack[self.stack_pointer];
+        Ok(())
     }
 
     const fn add_xy(&mut self, x: u8, y: u8) {
@@ -399,11 +670,13 @@ impl Chip8Emulator {
         self.i_register = addr;
     }
 
-    const fn jump_from(&mut self, addr: u16) {
+    const fn jump_from(&mut self, x: u8, addr: u16) {
         // Bnnn - JP V0, addr
         // Jump to location nnn + V0.
         // The program counter is set to nnn plus the value of V0.
-        self.program_counter = (self.v_registers[0] as u16) + addr;
+        // SUPER-CHIP reads this as Bxnn, using Vx as the offset.
+        let offset = if self.quirks.jump { x as usize } else { 0 };
+        self.program_counter = (self.v_registers[offset] as u16) + addr;
     }
 
     fn rand(&mut self, x: u8, byte: u8) {
@@ -411,11 +684,17 @@ impl Chip8Emulator {
         // Set Vx = random byte AND kk.
         // The interpreter generates a random number from 0 to 255, which is then ANDed with the value kk.
         // The results are stored in Vx. See instruction 8xy2 for more information on AND.
-        let r = fastrand::u8(..);
+        let r = self.rng.u8(..);
         self.v_registers[x as usize] = r & byte;
     }
 
-    fn display(&mut self, x: u8, y: u8, d: u8) {
+    fn display(
+        &mut self,
+        hooks: &mut impl Chip8Hooks,
+        x: u8,
+        y: u8,
+        d: u8,
+    ) -> Result<(), Chip8Error> {
         // Dxyn - DRW Vx, Vy, nibble
         // Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
         // The interpreter reads n bytes from memory, starting at the address stored in I.
@@ -426,60 +705,75 @@ impl Chip8Emulator {
         // it wraps around to the opposite side of the screen.
         // See instruction 8xy3 for more information on XOR, and section 2.4, Display, for more information on the Chip-8 screen and sprites.
 
-        // Implementation based on: <https://aquova.net/emudev/chip8/5-instr.html>
-        let x_coord = u16::from(self.v_registers[x as usize]);
-        let y_coord = u16::from(self.v_registers[y as usize]);
+        // Each sprite row is shifted into place and XORed onto a whole display row at once
+        let x_coord = u32::from(self.v_registers[x as usize]) % SCREEN_WIDTH as u32;
+        let y_coord = usize::from(self.v_registers[y as usize]) % SCREEN_HEIGHT;
+        // Rows clipped at the bottom of the screen are never read
+        let rows = if self.quirks.wrap {
+            usize::from(d)
+        } else {
+            usize::from(d).min(SCREEN_HEIGHT - y_coord)
+        };
+        let sprite_addrs = self.i_range(rows)?;
 
-        let num_rows = u16::from(d);
         let mut flipped = false;
-        // Iterate over each row of our sprite
-        for y_line in 0..num_rows {
-            // Determine which memory address our row's data is stored
-            let addr = self.i_register + y_line;
-            let pixels = self.memory[addr as usize];
-            // Iterate over each column in our row
-            for x_line in 0..8 {
-                // Use a mask to fetch current pixel's bit. Only flip if a 1
-                if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                    // Sprites should wrap around screen, so apply modulo
-                    let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                    let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
-                    // Get our pixel's index for our 1D screen array
-                    let idx = x + SCREEN_WIDTH * y;
-                    // Check if we're about to flip the pixel and set
-                    flipped |= self.display[idx];
-                    self.display[idx] ^= true;
-                }
-            }
+        for (y_line, addr) in sprite_addrs.enumerate() {
+            // Sprites should wrap around screen, so apply modulo
+            let y = (y_coord + y_line) % SCREEN_HEIGHT;
+            // The sprite row in the leftmost pixels, then moved across to its column
+            let pixels = self.memory[addr];
+            hooks.memory_read(addr as u16, pixels);
+            let sprite = u64::from(pixels) << (SCREEN_WIDTH - 8);
+            let sprite = if self.quirks.wrap {
+                sprite.rotate_right(x_coord)
+            } else {
+                sprite >> x_coord
+            };
+            // Check if we're about to flip any pixel and set
+            flipped |= self.display[y] & sprite != 0;
+            self.display[y] ^= sprite;
+            self.damage.mark_row(y, sprite);
         }
+        hooks.draw(x_coord as u8, y_coord as u8, d, flipped);
         // Populate VF register
         if flipped {
             self.v_registers[0xF] = 1;
         } else {
             self.v_registers[0xF] = 0;
         }
+        Ok(())
     }
 
-    const fn skip_if_key(&mut self, x: u8) {
+    const fn skip_if_key(&mut self, x: u8) -> Result<(), Chip8Error> {
         // Ex9E - SKP Vx
         // Skip next instruction if key with the value of Vx is pressed.
         // Checks the keyboard, and if the key corresponding to the value of Vx is currently in the down position, PC is increased by 2.
         let vx = self.v_registers[x as usize];
+        if vx as usize >= NUM_KEYS {
+            return Err(Chip8Error::InvalidKey(vx));
+        }
         let key_press = self.keyboard[vx as usize];
+        self.polled_keys |= 1 << vx;
         if key_press {
             self.program_counter += OPCODE_SIZE;
         }
+        Ok(())
     }
 
-    const fn skip_not_key(&mut self, x: u8) {
+    const fn skip_not_key(&mut self, x: u8) -> Result<(), Chip8Error> {
         // ExA1 - SKNP Vx
         // Skip next instruction if key with the value of Vx is not pressed.
         // Checks the keyboard, and if the key corresponding to the value of Vx is currently in the up position, PC is increased by 2.
         let vx = self.v_registers[x as usize];
+        if vx as usize >= NUM_KEYS {
+            return Err(Chip8Error::InvalidKey(vx));
+        }
         let key_press = self.keyboard[vx as usize];
+        self.polled_keys |= 1 << vx;
         if !key_press {
             self.program_counter += OPCODE_SIZE;
         }
+        Ok(())
     }
 
     const fn set_register_to_delay(&mut self, x: u8) {
@@ -489,42 +783,49 @@ impl Chip8Emulator {
         self.v_registers[x as usize] = self.delay_timer;
     }
 
-    fn wait_timer(&mut self, x: u8) {
+    fn wait_timer(&mut self, hooks: &mut impl Chip8Hooks, x: u8) {
         // Fx0A - LD Vx, K
         // Wait for a key press, store the value of the key in Vx.
         // All execution stops until a key is pressed, then the value of that key is stored in Vx.
-        let mut is_pressed = false;
+        self.polled_keys = u16::MAX;
+        let mut key = None;
         for (idx, pressed) in self.keyboard.iter().enumerate() {
             if *pressed {
                 self.v_registers[x as usize] = idx as u8;
-                is_pressed = true;
+                key = Some(idx as u8);
                 break;
             }
         }
-        if !is_pressed {
+        if key.is_none() {
             self.program_counter -= OPCODE_SIZE;
         }
+        hooks.key_wait(key);
     }
 
-    const fn set_timer(&mut self, x: u8) {
+    fn set_timer(&mut self, hooks: &mut impl Chip8Hooks, x: u8) {
         // Fx15 - LD DT, Vx
         // Set delay timer = Vx.
         // DT is set equal to the value of Vx.
         self.delay_timer = self.v_registers[x as usize];
+        hooks.timer_write(Timer::Delay, self.delay_timer);
     }
 
-    const fn set_sound_timer(&mut self, x: u8) {
+    fn set_sound_from_register(&mut self, hooks: &mut impl Chip8Hooks, x: u8) {
         // Fx18 - LD ST, Vx
         // Set sound timer = Vx.
         // ST is set equal to the value of Vx.
         self.sound_timer = self.v_registers[x as usize];
+        hooks.timer_write(Timer::Sound, self.sound_timer);
     }
 
     const fn add_to_i_register(&mut self, x: u8) {
         // Fx1E - ADD I, Vx
         // Set I = I + Vx.
         // The values of I and Vx are added, and the results are stored in I.
-        self.i_register += self.v_registers[x as usize] as u16;
+        // Running past the end of RAM is reported when memory is next accessed through I
+        self.i_register = self
+            .i_register
+            .wrapping_add(self.v_registers[x as usize] as u16);
     }
 
     const fn set_i_to_font_addr(&mut self, x: u8) {
@@ -537,7 +838,7 @@ impl Chip8Emulator {
         self.i_register = char * 5;
     }
 
-    fn store_bcd_encoding(&mut self, x: u8) {
+    fn store_bcd_encoding(&mut self, hooks: &mut impl Chip8Hooks, x: u8) -> Result<(), Chip8Error> {
         // Fx33 - LD B, Vx
         // Store BCD representation of Vx in memory locations I, I+1, and I+2.
         // The interpreter takes the decimal value of Vx, and places the hundreds digit in memory at location in I,
@@ -549,26 +850,48 @@ impl Chip8Emulator {
         let tenths = ((vx / 10.0) % 10.0).floor() as u8;
         let ones = (vx % 1.0).floor() as u8;
 
-        self.load_data_range(&[hundredths, tenths, ones], self.i_register as usize);
+        let range = self.i_range(3)?;
+        self.load_data_range(&[hundredths, tenths, ones], range.start);
+        for (addr, value) in range.zip([hundredths, tenths, ones]) {
+            hooks.memory_write(addr as u16, value);
+        }
+        Ok(())
     }
 
-    fn store_registers_at_i(&mut self, x: u8) {
+    fn store_registers_at_i(
+        &mut self,
+        hooks: &mut impl Chip8Hooks,
+        x: u8,
+    ) -> Result<(), Chip8Error> {
         // Fx55 - LD [I], Vx
         // Store registers V0 through Vx in memory starting at location I.
         // The interpreter copies the values of registers V0 through Vx into memory, starting at the address in I.
-        let i_addr = self.i_register as usize;
-        for offset in 0..=x as usize {
-            self.memory[i_addr + offset] = self.v_registers[x as usize];
+        for (reg_idx, addr) in self.i_range(usize::from(x) + 1)?.enumerate() {
+            self.memory[addr] = self.v_registers[reg_idx];
+            hooks.memory_write(addr as u16, self.v_registers[reg_idx]);
         }
+        if self.quirks.memory_increment {
+            self.i_register = self.i_register.wrapping_add(u16::from(x) + 1);
+        }
+        Ok(())
     }
 
-    fn load_registers_from_i_addr(&mut self, x: u8) {
+    fn load_registers_from_i_addr(
+        &mut self,
+        hooks: &mut impl Chip8Hooks,
+        x: u8,
+    ) -> Result<(), Chip8Error> {
         // Fx65 - LD Vx, [I]
         // Read registers V0 through Vx from memory starting at location I.
         // The interpreter reads values from memory starting at location I into registers V0 through Vx.
-        for reg_idx in 0..=x as usize {
-            self.v_registers[reg_idx] = self.memory[self.i_register as usize + reg_idx];
+        for (reg_idx, addr) in self.i_range(usize::from(x) + 1)?.enumerate() {
+            self.v_registers[reg_idx] = self.memory[addr];
+            hooks.memory_read(addr as u16, self.memory[addr]);
         }
+        if self.quirks.memory_increment {
+            self.i_register = self.i_register.wrapping_add(u16::from(x) + 1);
+        }
+        Ok(())
     }
 }
 
@@ -587,7 +910,7 @@ mod tests {
             0x21, 0x00, // Call (0x100)
             0x00, 0x00, // End
         ];
-        cpu.load_data(&data);
+        cpu.load_data(&data).unwrap();
 
         let func_data: [u8; 6] = [
             0x80, 0x14, // Add(0, 1)
@@ -603,11 +926,204 @@ mod tests {
         assert_eq!(cpu.v_registers[0], 45);
     }
 
+    #[test]
+    fn quirks_change_semantics() {
+        let mut cpu = Chip8Emulator::new();
+        cpu.set_quirks(Platform::Chip8.quirks());
+        cpu.v_registers[1] = 0b11;
+        cpu.v_registers[0xF] = 1;
+        cpu.i_register = 0x300;
+        cpu.load_data(&[
+            0x80, 0x16, // SHR V0, V1
+            0x82, 0x31, // OR V2, V3
+            0xF1, 0x55, // LD [I], V1
+            0x00, 0x00, // End
+        ])
+        .unwrap();
+        while cpu.tick().is_some() {}
+        assert_eq!(cpu.v_registers[0], 1);
+        assert_eq!(cpu.v_registers[0xF], 0);
+        assert_eq!(cpu.memory[0x300..0x302], [1, 0b11]);
+        assert_eq!(cpu.i_register, 0x302);
+    }
+
+    #[test]
+    fn sprites_wrap_or_clip_at_edges() {
+        // LD I, font 0; DRW V0, V1, 5
+        let program = [0xA0, 0x00, 0xD0, 0x15];
+        for wrap in [true, false] {
+            let mut cpu = Chip8Emulator::new();
+            cpu.set_quirks(Quirks {
+                wrap,
+                ..Quirks::default()
+            });
+            cpu.v_registers[0] = 62;
+            cpu.v_registers[1] = 30;
+            cpu.load_data(&program).unwrap();
+            cpu.run_frame(2);
+
+            // The top of the "0" is 0xF0, split across the right edge
+            assert_eq!(cpu.display_rows()[30] & 0b11, 0b11);
+            assert!(cpu.pixel(62, 30) && cpu.pixel(63, 30));
+            assert_eq!(cpu.pixel(0, 30), wrap);
+            assert_eq!(cpu.display_rows()[0] != 0, wrap);
+            assert_eq!(cpu.display_bytes()[30 * 8], if wrap { 0xC0 } else { 0 });
+            assert_eq!(
+                cpu.get_display().filter(|&pixel| pixel).count(),
+                if wrap { 14 } else { 3 }
+            );
+
+            // Drawing again erases it all
+            cpu.set_program_counter(0x202);
+            cpu.run_frame(1);
+            assert_eq!(cpu.v_registers[0xF], 1);
+            assert_eq!(cpu.display_rows(), &[0; SCREEN_HEIGHT]);
+        }
+    }
+
+    #[test]
+    fn faults_are_reported() {
+        let mut cpu = Chip8Emulator::new();
+        cpu.load_data(&[0x00, 0xEE]).unwrap();
+        assert_eq!(cpu.try_tick(), Err(Chip8Error::StackUnderflow));
+        cpu.load_data(&[0xE1, 0xFF]).unwrap();
+        cpu.program_counter = START_ADDR;
+        assert_eq!(
+            cpu.try_run_frame(10),
+            Err(Chip8Error::UnknownOpcode(0xE1FF))
+        );
+        assert_eq!(
+            cpu.load_data(&[0; RAM_SIZE]),
+            Err(Chip8Error::RomTooLarge { size: RAM_SIZE })
+        );
+    }
+
+    /// Run `setup`, then return what running `opcode` reports
+    fn fault(setup: &[u8], opcode: [u8; 2]) -> Result<Option<()>, Chip8Error> {
+        let mut cpu = Chip8Emulator::new();
+        cpu.load_data(&[setup, &opcode].concat()).unwrap();
+        for _ in 0..setup.len() / 2 {
+            cpu.try_tick().unwrap();
+        }
+        cpu.try_tick()
+    }
+
+    #[test]
+    fn memory_faults_are_reported() {
+        // LD I, 0xFFE
+        let near_end = [0xAF, 0xFE];
+        let out_of_bounds = Err(Chip8Error::MemoryOutOfBounds(0xFFE));
+        // LD [I], V2
+        assert_eq!(fault(&near_end, [0xF2, 0x55]), out_of_bounds);
+        // LD V2, [I]
+        assert_eq!(fault(&near_end, [0xF2, 0x65]), out_of_bounds);
+        // LD B, V0
+        assert_eq!(fault(&near_end, [0xF0, 0x33]), out_of_bounds);
+        // DRW V0, V0, 3
+        assert_eq!(fault(&near_end, [0xD0, 0x03]), out_of_bounds);
+        // LD V1, [I] just fits
+        assert_eq!(fault(&near_end, [0xF1, 0x65]), Ok(Some(())));
+    }
+
+    #[test]
+    fn sprites_only_read_drawn_rows() {
+        let mut cpu = Chip8Emulator::new();
+        // LD I, 0xFFE; LD V0, 31; DRW V0, V0, 3 clips all but one row off the bottom
+        cpu.load_data(&[0xAF, 0xFE, 0x60, 0x1F, 0xD0, 0x03])
+            .unwrap();
+        cpu.quirks.wrap = false;
+        assert_eq!(cpu.try_run_frame(3), Ok(Some(())));
+        cpu.quirks.wrap = true;
+        cpu.program_counter = START_ADDR;
+        assert_eq!(
+            cpu.try_run_frame(3),
+            Err(Chip8Error::MemoryOutOfBounds(0xFFE))
+        );
+    }
+
+    #[test]
+    fn i_past_the_end_of_ram_is_reported_on_access() {
+        // LD I, 0xFFF; LD V0, 0xFF; ADD I, V0
+        let setup = [0xAF, 0xFF, 0x60, 0xFF, 0xF0, 0x1E];
+        // LD V0, [I]
+        assert_eq!(
+            fault(&setup, [0xF0, 0x65]),
+            Err(Chip8Error::MemoryOutOfBounds(0x10FE))
+        );
+
+        let mut cpu = Chip8Emulator::new();
+        cpu.quirks.memory_increment = true;
+        // LD I, 0xFFF; LD [I], V0; LD [I], V0
+        cpu.load_data(&[0xAF, 0xFF, 0xF0, 0x55, 0xF0, 0x55])
+            .unwrap();
+        assert_eq!(cpu.try_run_frame(2), Ok(Some(())));
+        assert_eq!(cpu.i_register, 0x1000);
+        assert_eq!(cpu.try_tick(), Err(Chip8Error::MemoryOutOfBounds(0x1000)));
+    }
+
+    #[test]
+    fn key_faults_are_reported() {
+        // LD V0, 16
+        let setup = [0x60, 0x10];
+        // SKP V0
+        assert_eq!(fault(&setup, [0xE0, 0x9E]), Err(Chip8Error::InvalidKey(16)));
+        // SKNP V0
+        assert_eq!(fault(&setup, [0xE0, 0xA1]), Err(Chip8Error::InvalidKey(16)));
+    }
+
+    #[test]
+    fn polled_keys_are_tracked() {
+        let mut cpu = Chip8Emulator::new();
+        // LD V0, 5; SKP V0; LD V1, C; SKNP V1; CLS; LD V2, K
+        cpu.load_data(&[
+            0x60, 0x05, 0xE0, 0x9E, 0x61, 0x0C, 0xE1, 0xA1, 0x00, 0xE0, 0xF2, 0x0A,
+        ])
+        .unwrap();
+        cpu.run_frame(4);
+        assert_eq!(cpu.take_polled_keys(), 1 << 5 | 1 << 0xC);
+        assert_eq!(cpu.take_polled_keys(), 0);
+        cpu.run_frame(1);
+        assert_eq!(cpu.take_polled_keys(), u16::MAX);
+    }
+
+    #[test]
+    fn display_damage_is_tracked() {
+        let mut cpu = Chip8Emulator::new();
+        // LD V0, 10; LD V1, 3; LD I, font 0; DRW V0, V1, 5; LD V2, 1; CLS
+        cpu.load_data(&[
+            0x60, 0x0A, 0x61, 0x03, 0xA0, 0x00, 0xD0, 0x15, 0x62, 0x01, 0x00, 0xE0,
+        ])
+        .unwrap();
+        assert_eq!(cpu.take_display_damage(), DisplayDamage::FULL);
+        cpu.run_frame(3);
+        assert!(cpu.take_display_damage().is_empty());
+        cpu.run_frame(1);
+        assert_eq!(cpu.take_display_damage().bounds(), Some((10, 3, 4, 5)));
+        cpu.run_frame(1);
+        assert!(cpu.take_display_damage().is_empty());
+        cpu.run_frame(1);
+        assert_eq!(cpu.take_display_damage().bounds(), Some((10, 3, 4, 5)));
+        // Clearing a blank display changes nothing
+        cpu.set_program_counter(0x20A);
+        cpu.run_frame(1);
+        assert!(cpu.take_display_damage().is_empty());
+    }
+
+    #[test]
+    fn load_data_hashes_rom() {
+        let mut cpu = Chip8Emulator::new();
+        cpu.load_data(include_bytes!("../../roms/MAZE")).unwrap();
+        assert_eq!(
+            cpu.rom_hash(),
+            "b9272ae1acdaaa79ab649f6b48b72088ca2b1d74".parse().unwrap()
+        );
+    }
+
     #[test]
     fn load_rom_pong() {
         let mut cpu = Chip8Emulator::new();
         let bytes = include_bytes!("../../roms/PONG");
-        cpu.load_data(bytes);
+        cpu.load_data(bytes).unwrap();
         let mut counter = 0;
         while counter < 10000 {
             if cpu.tick().is_none() {
@@ -622,7 +1138,7 @@ mod tests {
     fn load_rom_guess() {
         let mut cpu = Chip8Emulator::new();
         let bytes = include_bytes!("../../roms/GUESS");
-        cpu.load_data(bytes);
+        cpu.load_data(bytes).unwrap();
         let mut counter = 0;
         while counter < 10000 {
             if cpu.tick().is_none() {
@@ -637,7 +1153,7 @@ mod tests {
     fn load_rom_maze() {
         let mut cpu = Chip8Emulator::new();
         let bytes = include_bytes!("../../roms/MAZE");
-        cpu.load_data(bytes);
+        cpu.load_data(bytes).unwrap();
         let mut counter = 0;
         while counter < 10000 {
             if cpu.tick().is_none() {
@@ -0,0 +1,469 @@
+//! Input movies: recordings of keypad input that replay deterministically.
+//!
+//! A movie stores everything that influences a run besides the ROM itself: the RNG seed,
+//! quirks, instructions per frame and every `keypress` tagged with the frame it happened
+//! before. State hashes taken every `checkpoint_interval` frames let playback detect when it
+//! has diverged from the recording.
+//!
+//! File layout, integers little-endian:
+//!
+//! | Field                 | Size                                        |
+//! |-----------------------|---------------------------------------------|
+//! | magic `C8M\x01`       | 4                                           |
+//! | ROM SHA-1             | 20                                          |
+//! | seed                  | 8                                           |
+//! | quirks bitfield       | 1                                           |
+//! | instructions/frame    | 4                                           |
+//! | frames                | 4                                           |
+//! | checkpoint interval   | 4                                           |
+//! | event count, events   | 4 + n * (LEB128 frame delta, key/pressed)   |
+//! | checkpoint count, hashes | 4 + n * 8                                |
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Chip8Emulator, Chip8Error, Chip8Hooks, NUM_KEYS, NoHooks, Quirks, RomHash};
+
+const MAGIC: &[u8; 4] = b"C8M\x01";
+
+/// Frames between state hashes in new recordings, one per second
+pub const DEFAULT_CHECKPOINT_INTERVAL: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieError {
+    /// Not a movie file, or a newer version of the format
+    BadMagic,
+    /// The file ended before all fields were read
+    Truncated,
+    /// A key index outside the keypad
+    InvalidKey(u8),
+    /// The movie was recorded with a different ROM
+    RomMismatch { expected: RomHash, actual: RomHash },
+    /// Playback state no longer matches the recording
+    Desync { frame: u32 },
+    /// The program faulted while recording or replaying
+    Emulator(Chip8Error),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a chip-8 movie"),
+            Self::Truncated => write!(f, "movie file is truncated"),
+            Self::InvalidKey(key) => write!(f, "movie presses key {key}, outside the keypad"),
+            Self::RomMismatch { expected, actual } => {
+                write!(f, "movie was recorded with ROM {expected}, not {actual}")
+            }
+            Self::Desync { frame } => write!(f, "playback desynced at frame {frame}"),
+            Self::Emulator(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl core::error::Error for MovieError {}
+
+impl From<Chip8Error> for MovieError {
+    fn from(err: Chip8Error) -> Self {
+        Self::Emulator(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputEvent {
+    /// Number of frames completed before the key changed
+    pub frame: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: RomHash,
+    pub seed: u64,
+    pub quirks: Quirks,
+    /// Instructions per frame
+    pub ipf: u32,
+    /// Frames recorded
+    pub frames: u32,
+    /// In frame order when recorded, and written in frame order whatever order they are in
+    pub events: Vec<InputEvent>,
+    pub checkpoint_interval: u32,
+    /// `state_hash` after every `checkpoint_interval` frames
+    pub checkpoints: Vec<u64>,
+}
+
+impl Movie {
+    /// Encode the movie, with events sorted by frame. Events in the same frame keep their order.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + self.events.len() * 2 + self.checkpoints.len() * 8);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(self.rom_hash.as_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.push(self.quirks.to_bits());
+        out.extend_from_slice(&self.ipf.to_le_bytes());
+        out.extend_from_slice(&self.frames.to_le_bytes());
+        out.extend_from_slice(&self.checkpoint_interval.to_le_bytes());
+
+        out.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+        // Frames are stored as deltas, which must not go backwards
+        let mut events = self.events.clone();
+        events.sort_by_key(|event| event.frame);
+        let mut last_frame = 0;
+        for event in &events {
+            write_leb128(&mut out, event.frame - last_frame);
+            out.push(event.key | (u8::from(event.pressed) << 7));
+            last_frame = event.frame;
+        }
+
+        out.extend_from_slice(&(self.checkpoints.len() as u32).to_le_bytes());
+        for hash in &self.checkpoints {
+            out.extend_from_slice(&hash.to_le_bytes());
+        }
+        out
+    }
+
+    /// # Errors
+    /// - the data is not a complete movie
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = Reader(data);
+        if reader.take::<4>()? != *MAGIC {
+            return Err(MovieError::BadMagic);
+        }
+        let rom_hash = RomHash::from_bytes(reader.take()?);
+        let seed = u64::from_le_bytes(reader.take()?);
+        let quirks = Quirks::from_bits(reader.take::<1>()?[0]);
+        let ipf = reader.u32()?;
+        let frames = reader.u32()?;
+        let checkpoint_interval = reader.u32()?;
+
+        let event_count = reader.u32()?;
+        let mut events = Vec::new();
+        let mut frame = 0u32;
+        for _ in 0..event_count {
+            frame = frame.saturating_add(reader.leb128()?);
+            let byte = reader.take::<1>()?[0];
+            let key = byte & 0x7F;
+            if key as usize >= NUM_KEYS {
+                return Err(MovieError::InvalidKey(key));
+            }
+            events.push(InputEvent {
+                frame,
+                key,
+                pressed: byte & 0x80 != 0,
+            });
+        }
+
+        let checkpoint_count = reader.u32()?;
+        let mut checkpoints = Vec::new();
+        for _ in 0..checkpoint_count {
+            checkpoints.push(u64::from_le_bytes(reader.take()?));
+        }
+
+        Ok(Self {
+            rom_hash,
+            seed,
+            quirks,
+            ipf,
+            frames,
+            events,
+            checkpoint_interval,
+            checkpoints,
+        })
+    }
+}
+
+/// Captures input while driving an emulator frame by frame
+#[derive(Debug)]
+pub struct Recorder {
+    movie: Movie,
+}
+
+impl Recorder {
+    /// Start recording a freshly loaded emulator. The RNG is restarted from its seed so the
+    /// run can be reproduced.
+    pub fn new(emulator: &mut Chip8Emulator, ipf: u32) -> Self {
+        emulator.set_seed(emulator.seed());
+        Self {
+            movie: Movie {
+                rom_hash: emulator.rom_hash(),
+                seed: emulator.seed(),
+                quirks: emulator.quirks(),
+                ipf,
+                frames: 0,
+                events: Vec::new(),
+                checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+                checkpoints: Vec::new(),
+            },
+        }
+    }
+
+    /// Press or release a key before the next frame
+    pub fn keypress(&mut self, emulator: &mut Chip8Emulator, idx: usize, pressed: bool) {
+        emulator.keypress(idx, pressed);
+        self.movie.events.push(InputEvent {
+            frame: self.movie.frames,
+            key: idx as u8,
+            pressed,
+        });
+    }
+
+    /// Run one frame, returning `None` once the program has halted
+    ///
+    /// # Errors
+    /// See [`Chip8Emulator::try_tick`]
+    pub fn run_frame(&mut self, emulator: &mut Chip8Emulator) -> Result<Option<()>, Chip8Error> {
+        self.run_frame_with(emulator, &mut NoHooks)
+    }
+
+    /// [`Self::run_frame`] reporting what happens to `hooks`
+    ///
+    /// # Errors
+    /// See [`Chip8Emulator::try_tick`]
+    pub fn run_frame_with<H: Chip8Hooks>(
+        &mut self,
+        emulator: &mut Chip8Emulator,
+        hooks: &mut H,
+    ) -> Result<Option<()>, Chip8Error> {
+        let running = emulator.try_run_frame_with(self.movie.ipf, hooks)?;
+        self.movie.frames += 1;
+        if self
+            .movie
+            .frames
+            .is_multiple_of(self.movie.checkpoint_interval)
+        {
+            self.movie.checkpoints.push(emulator.state_hash());
+        }
+        Ok(running)
+    }
+
+    #[must_use]
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+/// Replays a movie into an emulator, checking it stays in sync
+#[derive(Debug)]
+pub struct Player {
+    movie: Movie,
+    frame: u32,
+    next_event: usize,
+}
+
+impl Player {
+    /// Configure a freshly loaded emulator to match the recording
+    ///
+    /// # Errors
+    /// - the emulator holds a different ROM than the movie was recorded with
+    pub fn new(movie: Movie, emulator: &mut Chip8Emulator) -> Result<Self, MovieError> {
+        if movie.rom_hash != emulator.rom_hash() {
+            return Err(MovieError::RomMismatch {
+                expected: movie.rom_hash,
+                actual: emulator.rom_hash(),
+            });
+        }
+        emulator.set_seed(movie.seed);
+        emulator.set_quirks(movie.quirks);
+        Ok(Self {
+            movie,
+            frame: 0,
+            next_event: 0,
+        })
+    }
+
+    #[must_use]
+    pub const fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    #[must_use]
+    pub const fn is_finished(&self) -> bool {
+        self.frame >= self.movie.frames
+    }
+
+    #[must_use]
+    pub const fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    /// Apply this frame's input and run it. Returns `Ok(false)` once the recording is over.
+    ///
+    /// # Errors
+    /// - the emulator state differs from the recording at a checkpoint
+    /// - the program faults
+    pub fn run_frame(&mut self, emulator: &mut Chip8Emulator) -> Result<bool, MovieError> {
+        self.run_frame_with(emulator, &mut NoHooks)
+    }
+
+    /// [`Self::run_frame`] reporting what happens to `hooks`
+    ///
+    /// # Errors
+    /// - the emulator state differs from the recording at a checkpoint
+    /// - the program faults
+    pub fn run_frame_with<H: Chip8Hooks>(
+        &mut self,
+        emulator: &mut Chip8Emulator,
+        hooks: &mut H,
+    ) -> Result<bool, MovieError> {
+        if self.is_finished() {
+            return Ok(false);
+        }
+        while let Some(event) = self.movie.events.get(self.next_event)
+            && event.frame == self.frame
+        {
+            emulator.keypress(event.key as usize, event.pressed);
+            self.next_event += 1;
+        }
+        emulator.try_run_frame_with(self.movie.ipf, hooks)?;
+        self.frame += 1;
+        // An interval of zero means the movie carries no checkpoints
+        if let Some(idx) = self.frame.checked_div(self.movie.checkpoint_interval)
+            && self.frame.is_multiple_of(self.movie.checkpoint_interval)
+            && let Some(&expected) = self.movie.checkpoints.get(idx as usize - 1)
+            && expected != emulator.state_hash()
+        {
+            return Err(MovieError::Desync { frame: self.frame });
+        }
+        Ok(true)
+    }
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], MovieError> {
+        let (head, tail) = self.0.split_first_chunk().ok_or(MovieError::Truncated)?;
+        self.0 = tail;
+        Ok(*head)
+    }
+
+    fn u32(&mut self) -> Result<u32, MovieError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn leb128(&mut self) -> Result<u32, MovieError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.take::<1>()?[0];
+            value |= u32::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_pong() -> Movie {
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(include_bytes!("../../roms/PONG"))
+            .unwrap();
+        let mut recorder = Recorder::new(&mut emulator, 10);
+        for frame in 0..300 {
+            if frame % 40 == 0 {
+                recorder.keypress(&mut emulator, 1, frame % 80 == 0);
+            }
+            recorder.run_frame(&mut emulator).unwrap();
+        }
+        recorder.finish()
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let movie = record_pong();
+        assert_eq!(movie.checkpoints.len(), 5);
+        assert_eq!(Movie::from_bytes(&movie.to_bytes()), Ok(movie));
+        assert_eq!(Movie::from_bytes(b"C8M"), Err(MovieError::Truncated));
+        assert_eq!(Movie::from_bytes(b"nope"), Err(MovieError::BadMagic));
+    }
+
+    #[test]
+    fn writes_events_in_frame_order() {
+        let mut movie = record_pong();
+        movie.events.reverse();
+        let read = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        assert!(read.events.is_sorted_by_key(|event| event.frame));
+        movie.events.sort_by_key(|event| event.frame);
+        assert_eq!(read, movie);
+    }
+
+    #[test]
+    fn replays_in_sync() {
+        let movie = record_pong();
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(include_bytes!("../../roms/PONG"))
+            .unwrap();
+        let mut player = Player::new(movie, &mut emulator).unwrap();
+        while player.run_frame(&mut emulator).unwrap() {}
+        assert_eq!(player.frame(), 300);
+    }
+
+    #[test]
+    fn detects_desync_and_wrong_rom() {
+        let mut movie = record_pong();
+        movie.checkpoints[1] ^= 1;
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(include_bytes!("../../roms/PONG"))
+            .unwrap();
+        let mut player = Player::new(movie.clone(), &mut emulator).unwrap();
+        let result = loop {
+            match player.run_frame(&mut emulator) {
+                Ok(true) => {}
+                other => break other,
+            }
+        };
+        assert_eq!(result, Err(MovieError::Desync { frame: 120 }));
+
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(include_bytes!("../../roms/MAZE"))
+            .unwrap();
+        assert!(matches!(
+            Player::new(movie, &mut emulator),
+            Err(MovieError::RomMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn reports_faults() {
+        // An unknown opcode
+        let rom = [0x00, 0xFF];
+        let mut emulator = Chip8Emulator::new();
+        emulator.load_data(&rom).unwrap();
+        let mut recorder = Recorder::new(&mut emulator, 10);
+        assert_eq!(
+            recorder.run_frame(&mut emulator),
+            Err(Chip8Error::UnknownOpcode(0x00FF))
+        );
+
+        let mut movie = recorder.finish();
+        movie.frames = 1;
+        let mut emulator = Chip8Emulator::new();
+        emulator.load_data(&rom).unwrap();
+        let mut player = Player::new(movie, &mut emulator).unwrap();
+        assert_eq!(
+            player.run_frame(&mut emulator),
+            Err(MovieError::Emulator(Chip8Error::UnknownOpcode(0x00FF)))
+        );
+    }
+}
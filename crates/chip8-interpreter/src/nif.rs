@@ -0,0 +1,151 @@
+//! Erlang NIFs exposing the interpreter to the BEAM, loaded by `ui/src/libchip8_interpreter.erl`.
+//!
+//! Results are encoded the way Gleam represents `Result(a, Chip8Error)`: `{ok, Value}` or
+//! `{error, Reason}`, with `nil` standing in for `Nil`. Faults in the ROM are returned as errors
+//! instead of raising, so a broken program cannot take down the calling process.
+
+use rustler::{Atom, Binary, Encoder, Env, OwnedBinary, ResourceArc, Term};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{Chip8Emulator, Chip8Error, NUM_KEYS};
+
+mod atoms {
+    rustler::atoms! {
+        nil,
+        rom_too_large,
+        stack_overflow,
+        stack_underflow,
+        unknown_opcode,
+        program_counter_out_of_bounds,
+        memory_out_of_bounds,
+        invalid_key,
+        invalid_state,
+    }
+}
+
+/// Emulator shared with the BEAM, which may call in from any scheduler thread
+pub struct Emulator(Mutex<Chip8Emulator>);
+
+#[rustler::resource_impl]
+impl rustler::Resource for Emulator {}
+
+impl Emulator {
+    fn lock(&self) -> MutexGuard<'_, Chip8Emulator> {
+        // A panic mid-instruction leaves the machine in a state the program can still inspect
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Encoder for Chip8Error {
+    fn encode<'a>(&self, env: Env<'a>) -> Term<'a> {
+        match *self {
+            Self::RomTooLarge { size } => (atoms::rom_too_large(), size).encode(env),
+            Self::StackOverflow => atoms::stack_overflow().encode(env),
+            Self::StackUnderflow => atoms::stack_underflow().encode(env),
+            Self::UnknownOpcode(opcode) => (atoms::unknown_opcode(), opcode).encode(env),
+            Self::ProgramCounterOutOfBounds(pc) => {
+                (atoms::program_counter_out_of_bounds(), pc).encode(env)
+            }
+            Self::MemoryOutOfBounds(i) => (atoms::memory_out_of_bounds(), i).encode(env),
+            Self::InvalidKey(key) => (atoms::invalid_key(), key).encode(env),
+            Self::InvalidState => atoms::invalid_state().encode(env),
+        }
+    }
+}
+
+type Handle = ResourceArc<Emulator>;
+
+#[rustler::nif]
+fn new() -> Handle {
+    ResourceArc::new(Emulator(Mutex::new(Chip8Emulator::new())))
+}
+
+#[rustler::nif]
+fn load_rom(emulator: Handle, rom: Binary) -> Result<Atom, Chip8Error> {
+    emulator.lock().load_data(rom.as_slice())?;
+    Ok(atoms::nil())
+}
+
+/// `{ok, false}` once the program has halted
+#[rustler::nif]
+fn tick(emulator: Handle) -> Result<bool, Chip8Error> {
+    Ok(emulator.lock().try_tick()?.is_some())
+}
+
+/// `{ok, false}` once the program has halted
+#[rustler::nif]
+fn run_frame(emulator: Handle, ipf: u32) -> Result<bool, Chip8Error> {
+    Ok(emulator.lock().try_run_frame(ipf)?.is_some())
+}
+
+/// Several frames at once on a dirty scheduler, for hosts catching up after falling behind.
+/// `{ok, false}` if the program has halted by the last frame.
+#[rustler::nif(schedule = "DirtyCpu")]
+fn run_frames(emulator: Handle, frames: u32, ipf: u32) -> Result<bool, Chip8Error> {
+    let mut emulator = emulator.lock();
+    let mut running = true;
+    for _ in 0..frames {
+        running = emulator.try_run_frame(ipf)?.is_some();
+    }
+    Ok(running)
+}
+
+#[rustler::nif]
+fn tick_timers(emulator: Handle) -> Atom {
+    emulator.lock().tick_timers();
+    atoms::nil()
+}
+
+#[rustler::nif]
+fn keypress(emulator: Handle, key: u8, pressed: bool) -> Result<Atom, Chip8Error> {
+    if usize::from(key) >= NUM_KEYS {
+        return Err(Chip8Error::InvalidKey(key));
+    }
+    emulator.lock().keypress(usize::from(key), pressed);
+    Ok(atoms::nil())
+}
+
+/// One byte per pixel, 0 or 1, row-major from the top left
+#[rustler::nif]
+fn get_display(env: Env, emulator: Handle) -> Binary {
+    let emulator = emulator.lock();
+    let display = emulator.get_display();
+    let mut binary = OwnedBinary::new(display.len()).expect("allocating the display binary");
+    for (byte, pixel) in binary.as_mut_slice().iter_mut().zip(display) {
+        *byte = u8::from(pixel);
+    }
+    binary.release(env)
+}
+
+/// One bit per pixel, row-major from the top left, an eighth of the size of `get_display`
+#[rustler::nif]
+fn get_display_packed(env: Env, emulator: Handle) -> Binary {
+    let packed = emulator.lock().display_bytes();
+    let mut binary = OwnedBinary::new(packed.len()).expect("allocating the display binary");
+    binary.as_mut_slice().copy_from_slice(&packed);
+    binary.release(env)
+}
+
+/// `{Rows, Columns}` bitfields of the display changed since the last call, row 0 in the lowest
+/// bit and column 0 in the highest of 64, so hosts can skip fetching unchanged frames
+#[rustler::nif]
+fn take_display_damage(emulator: Handle) -> (u32, u64) {
+    let damage = emulator.lock().take_display_damage();
+    (damage.rows(), damage.columns())
+}
+
+#[rustler::nif]
+fn save_state(env: Env, emulator: Handle) -> Binary {
+    let state = emulator.lock().save_state();
+    let mut binary = OwnedBinary::new(state.len()).expect("allocating the save state binary");
+    binary.as_mut_slice().copy_from_slice(&state);
+    binary.release(env)
+}
+
+#[rustler::nif]
+fn load_state(emulator: Handle, state: Binary) -> Result<Atom, Chip8Error> {
+    emulator.lock().load_state(state.as_slice())?;
+    Ok(atoms::nil())
+}
+
+rustler::init!("libchip8_interpreter");
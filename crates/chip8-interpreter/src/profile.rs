@@ -0,0 +1,257 @@
+//! Where a program spends its time, counted in instructions executed.
+//!
+//! A [`Profiler`] is a set of [`Chip8Hooks`] counting executions per address and per opcode
+//! pattern. It follows `CALL` and `RET` to charge every instruction to the chain of subroutines
+//! running it, which gives time per subroutine and a call tree.
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::{Chip8Emulator, Chip8Hooks, RAM_SIZE, START_ADDR, disasm::Instruction};
+
+/// Instructions run by a subroutine, see [`Profiler::subroutines`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubroutineTime {
+    pub addr: u16,
+    pub calls: u64,
+    /// Instructions run in the subroutine itself
+    pub own: u64,
+    /// Instructions run in the subroutine and everything it called
+    pub total: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    executions: Vec<u64>,
+    patterns: BTreeMap<&'static str, u64>,
+    calls: BTreeMap<u16, u64>,
+    // Instructions run under each chain of subroutines, outermost first, with an index by chain
+    // so the count to add to only changes on CALL and RET
+    paths: Vec<(Vec<u16>, u64)>,
+    path_index: BTreeMap<Vec<u16>, usize>,
+    current: usize,
+    // Frames spent waiting on the Fx0A at each address
+    wait_frames: BTreeMap<u16, u64>,
+    waiting: Option<u16>,
+    last_pc: u16,
+    frames: u64,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            executions: vec![0; RAM_SIZE],
+            patterns: BTreeMap::new(),
+            calls: BTreeMap::new(),
+            paths: vec![(Vec::new(), 0)],
+            path_index: BTreeMap::from([(Vec::new(), 0)]),
+            current: 0,
+            wait_frames: BTreeMap::new(),
+            waiting: None,
+            last_pc: START_ADDR,
+            frames: 0,
+        }
+    }
+}
+
+impl Profiler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Frames run while profiling
+    #[must_use]
+    pub const fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Instructions executed
+    #[must_use]
+    pub fn instructions(&self) -> u64 {
+        self.paths.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Times the instruction at each address ran, indexed by address
+    #[must_use]
+    pub fn executions(&self) -> &[u64] {
+        &self.executions
+    }
+
+    /// Instructions executed of each opcode pattern, see [`Instruction::pattern`]
+    #[must_use]
+    pub const fn patterns(&self) -> &BTreeMap<&'static str, u64> {
+        &self.patterns
+    }
+
+    /// Frames that ended with the program waiting for a key, by address of the `Fx0A`
+    #[must_use]
+    pub const fn wait_frames(&self) -> &BTreeMap<u16, u64> {
+        &self.wait_frames
+    }
+
+    /// Chains of subroutines, outermost first and empty for code outside any, with the
+    /// instructions run in the innermost one. Each chain follows its callers, ordered by address.
+    pub fn call_paths(&self) -> impl Iterator<Item = (&[u16], u64)> {
+        self.path_index
+            .values()
+            .map(|&idx| (self.paths[idx].0.as_slice(), self.paths[idx].1))
+    }
+
+    /// Time in each subroutine called, by address. Recursion is only counted once towards the
+    /// total.
+    #[must_use]
+    pub fn subroutines(&self) -> Vec<SubroutineTime> {
+        let mut times: BTreeMap<u16, SubroutineTime> = self
+            .calls
+            .iter()
+            .map(|(&addr, &calls)| {
+                let time = SubroutineTime {
+                    addr,
+                    calls,
+                    own: 0,
+                    total: 0,
+                };
+                (addr, time)
+            })
+            .collect();
+        // Every subroutine in a chain was called, so each has an entry
+        for (path, count) in &self.paths {
+            if let Some(time) = path.last().and_then(|innermost| times.get_mut(innermost)) {
+                time.own += count;
+            }
+            for (depth, addr) in path.iter().enumerate() {
+                if let Some(time) = times.get_mut(addr)
+                    && !path[..depth].contains(addr)
+                {
+                    time.total += count;
+                }
+            }
+        }
+        times.into_values().collect()
+    }
+
+    /// Folded stacks for flamegraph tools: a line per chain of subroutines like
+    /// `main;sub_2A0;sub_2C4 120`, counting instructions
+    #[must_use]
+    pub fn to_folded(&self) -> String {
+        let mut out = String::new();
+        for (path, count) in self.call_paths().filter(|(_, count)| *count > 0) {
+            out.push_str("main");
+            for addr in path {
+                let _ = write!(out, ";sub_{addr:03X}");
+            }
+            let _ = writeln!(out, " {count}");
+        }
+        out
+    }
+
+    fn enter(&mut self, path: Vec<u16>) {
+        self.current = if let Some(&idx) = self.path_index.get(&path) {
+            idx
+        } else {
+            self.paths.push((path.clone(), 0));
+            self.path_index.insert(path, self.paths.len() - 1);
+            self.paths.len() - 1
+        };
+    }
+}
+
+impl Chip8Hooks for Profiler {
+    fn pre_instruction(&mut self, _emulator: &Chip8Emulator, pc: u16, opcode: u16) {
+        if let Some(count) = self.executions.get_mut(usize::from(pc)) {
+            *count += 1;
+        }
+        *self
+            .patterns
+            .entry(Instruction::decode(opcode).pattern())
+            .or_default() += 1;
+        self.paths[self.current].1 += 1;
+        self.last_pc = pc;
+    }
+
+    fn post_instruction(&mut self, emulator: &Chip8Emulator, _pc: u16, opcode: u16) {
+        match Instruction::decode(opcode) {
+            Instruction::Call(addr) => {
+                *self.calls.entry(addr).or_default() += 1;
+                let mut path = self.paths[self.current].0.clone();
+                path.push(addr);
+                self.enter(path);
+            }
+            Instruction::Ret => {
+                // Follow the emulator's stack, in case the chain was entered before profiling
+                let mut path = self.paths[self.current].0.clone();
+                path.truncate(emulator.stack().len());
+                self.enter(path);
+            }
+            _ => {}
+        }
+    }
+
+    fn key_wait(&mut self, key: Option<u8>) {
+        self.waiting = key.is_none().then_some(self.last_pc);
+    }
+
+    fn end_frame(&mut self, _emulator: &Chip8Emulator) {
+        self.frames += 1;
+        if let Some(addr) = self.waiting {
+            *self.wait_frames.entry(addr).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charges_time_to_subroutines() {
+        let mut emulator = Chip8Emulator::new();
+        emulator
+            .load_data(&[
+                0x22, 0x06, // 200: CALL 206
+                0xF0, 0x0A, // 202: LD V0, K
+                0x12, 0x02, // 204: JP 202
+                0x22, 0x0C, // 206: CALL 20C
+                0x60, 0x01, // 208: LD V0, 1
+                0x00, 0xEE, // 20A: RET
+                0x61, 0x02, // 20C: LD V1, 2
+                0x00, 0xEE, // 20E: RET
+            ])
+            .unwrap();
+        let mut profiler = Profiler::new();
+        for _ in 0..3 {
+            emulator.run_frame_with(5, &mut profiler);
+        }
+
+        assert_eq!(profiler.frames(), 3);
+        assert_eq!(profiler.instructions(), 15);
+        assert_eq!(profiler.executions()[0x202], 9);
+        assert_eq!(profiler.executions()[0x20C], 1);
+        assert_eq!(profiler.patterns()["2nnn"], 2);
+        assert_eq!(profiler.patterns()["Fx0A"], 9);
+        // Waiting by the end of the last two frames
+        assert_eq!(profiler.wait_frames()[&0x202], 2);
+        assert_eq!(
+            profiler.subroutines(),
+            [
+                SubroutineTime {
+                    addr: 0x206,
+                    calls: 1,
+                    own: 3,
+                    total: 5,
+                },
+                SubroutineTime {
+                    addr: 0x20C,
+                    calls: 1,
+                    own: 2,
+                    total: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            profiler.to_folded(),
+            "main 10\nmain;sub_206 3\nmain;sub_206;sub_20C 2\n"
+        );
+    }
+}
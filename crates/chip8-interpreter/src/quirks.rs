@@ -0,0 +1,110 @@
+//! Behavioural differences between Chip-8 interpreters.
+//!
+//! ROMs written for the COSMAC VIP, HP48 SUPER-CHIP and XO-CHIP rely on subtly different
+//! instruction semantics. See <https://github.com/chip-8/chip-8-database> for the naming.
+
+use core::fmt;
+
+/// Interpreter family a ROM was written for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// Original COSMAC VIP interpreter
+    #[default]
+    Chip8,
+    /// HP48 SUPER-CHIP 1.1
+    SuperChip,
+    /// Octo's XO-CHIP extension
+    XoChip,
+}
+
+impl Platform {
+    /// Quirks matching the reference interpreter of the platform
+    #[must_use]
+    pub const fn quirks(self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks {
+                shift: false,
+                memory_increment: true,
+                jump: false,
+                vf_reset: true,
+                wrap: false,
+            },
+            Self::SuperChip => Quirks {
+                shift: true,
+                memory_increment: false,
+                jump: true,
+                vf_reset: false,
+                wrap: false,
+            },
+            Self::XoChip => Quirks {
+                shift: false,
+                memory_increment: true,
+                jump: false,
+                vf_reset: false,
+                wrap: true,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Chip8 => "CHIP-8",
+            Self::SuperChip => "SUPER-CHIP",
+            Self::XoChip => "XO-CHIP",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Toggles for instruction semantics that differ between platforms.
+///
+/// The default matches this interpreter's historical behaviour, which follows Cowgod's reference.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift Vx in place instead of shifting Vy into Vx
+    pub shift: bool,
+    /// `Fx55`/`Fx65` leave I pointing past the last register transferred
+    pub memory_increment: bool,
+    /// `Bnnn` behaves as `Bxnn`, jumping to `xnn + Vx`
+    pub jump: bool,
+    /// `8xy1`/`8xy2`/`8xy3` reset VF to 0
+    pub vf_reset: bool,
+    /// Sprites drawn across the screen edge wrap around instead of being clipped
+    pub wrap: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            shift: true,
+            memory_increment: false,
+            jump: false,
+            vf_reset: false,
+            wrap: true,
+        }
+    }
+}
+
+impl Quirks {
+    /// Pack into one byte for the movie and save state formats
+    pub(crate) fn to_bits(self) -> u8 {
+        u8::from(self.shift)
+            | u8::from(self.memory_increment) << 1
+            | u8::from(self.jump) << 2
+            | u8::from(self.vf_reset) << 3
+            | u8::from(self.wrap) << 4
+    }
+
+    pub(crate) const fn from_bits(bits: u8) -> Self {
+        Self {
+            shift: bits & 1 != 0,
+            memory_increment: bits & 1 << 1 != 0,
+            jump: bits & 1 << 2 != 0,
+            vf_reset: bits & 1 << 3 != 0,
+            wrap: bits & 1 << 4 != 0,
+        }
+    }
+}
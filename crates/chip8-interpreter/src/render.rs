@@ -0,0 +1,338 @@
+//! Turning the 1-bit display into colours every frontend draws the same way.
+//!
+//! [`Phosphor`] keeps a brightness per pixel that fades rather than switching off at once, like the
+//! phosphor of a CRT. Sprites that are erased and redrawn with XOR then stay visible instead of
+//! flickering, and a [`Palette`] maps brightness to colour.
+
+use alloc::{format, vec, vec::Vec};
+use core::{fmt, str::FromStr};
+
+use crate::{Chip8Emulator, DisplayDamage, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const PIXELS: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    #[must_use]
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Mix towards `other` by `amount` out of 255
+    #[must_use]
+    pub const fn mix(self, other: Self, amount: u8) -> Self {
+        const fn channel(from: u8, to: u8, amount: u8) -> u8 {
+            let (from, to, amount) = (from as i32, to as i32, amount as i32);
+            (from + (to - from) * amount / 255) as u8
+        }
+        Self {
+            r: channel(self.r, other.r, amount),
+            g: channel(self.g, other.g, amount),
+            b: channel(self.b, other.b, amount),
+        }
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = ParsePaletteError;
+
+    /// `#rrggbb`, the `#` optional
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(ParsePaletteError);
+        }
+        let value = u32::from_str_radix(hex, 16).map_err(|_| ParsePaletteError)?;
+        let [_, r, g, b] = value.to_be_bytes();
+        Ok(Self { r, g, b })
+    }
+}
+
+/// Colours of unlit and lit pixels, with everything in between used as pixels fade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Palette {
+    pub off: Rgb,
+    pub on: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+impl Palette {
+    /// White on black
+    pub const CLASSIC: Self = Self::new(Rgb::new(0x00, 0x00, 0x00), Rgb::new(0xFF, 0xFF, 0xFF));
+    /// Amber monochrome monitor
+    pub const AMBER: Self = Self::new(Rgb::new(0x1A, 0x0E, 0x00), Rgb::new(0xFF, 0xB0, 0x00));
+    /// Green monochrome monitor
+    pub const GREEN: Self = Self::new(Rgb::new(0x00, 0x14, 0x00), Rgb::new(0x33, 0xFF, 0x66));
+    /// Dark pixels on a pea-green handheld LCD
+    pub const LCD: Self = Self::new(Rgb::new(0x9B, 0xBC, 0x0F), Rgb::new(0x0F, 0x38, 0x0F));
+
+    /// The palettes with names, for pickers
+    pub const NAMED: [(&'static str, Self); 4] = [
+        ("classic", Self::CLASSIC),
+        ("amber", Self::AMBER),
+        ("green", Self::GREEN),
+        ("lcd", Self::LCD),
+    ];
+
+    #[must_use]
+    pub const fn new(off: Rgb, on: Rgb) -> Self {
+        Self { off, on }
+    }
+
+    /// Colour of a pixel at `intensity`, 0 unlit to 255 fully lit
+    #[must_use]
+    pub const fn color(&self, intensity: u8) -> Rgb {
+        self.off.mix(self.on, intensity)
+    }
+}
+
+impl fmt::Display for Palette {
+    /// `off,on`, which [`Palette::from_str`] reads back
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.off, self.on)
+    }
+}
+
+/// Returned for a palette that is neither a known name nor two `#rrggbb` colours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsePaletteError;
+
+impl fmt::Display for ParsePaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "expected classic, amber, green, lcd or two colours like #000000,#ffffff"
+        )
+    }
+}
+
+impl core::error::Error for ParsePaletteError {}
+
+impl FromStr for Palette {
+    type Err = ParsePaletteError;
+
+    /// A name from [`Self::NAMED`], or custom `off,on` colours
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, palette)) = Self::NAMED
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+        {
+            return Ok(*palette);
+        }
+        let (off, on) = s.split_once(',').ok_or(ParsePaletteError)?;
+        Ok(Self::new(off.trim().parse()?, on.trim().parse()?))
+    }
+}
+
+/// Brightness of every pixel, following the display from frame to frame
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    intensity: [u8; PIXELS],
+    decay: u8,
+    // Whether some pixel is part way to dark, and so changes next frame
+    fading: bool,
+}
+
+impl Default for Phosphor {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Phosphor {
+    /// Brightness kept from one frame to the next by a pixel that went dark, fading it out over a
+    /// few frames
+    pub const PERSISTENCE: u8 = 160;
+
+    /// Start dark. `decay` is the brightness out of 256 an unlit pixel keeps each frame, so 0
+    /// follows the display exactly and [`Self::PERSISTENCE`] smooths out flicker.
+    #[must_use]
+    pub const fn new(decay: u8) -> Self {
+        Self {
+            intensity: [0; PIXELS],
+            decay,
+            fading: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn decay(&self) -> u8 {
+        self.decay
+    }
+
+    pub const fn set_decay(&mut self, decay: u8) {
+        self.decay = decay;
+    }
+
+    /// Take in the display at the end of a frame, with the damage the emulator reported since the
+    /// last update: lit pixels are at full brightness, the rest fade. Returns where brightness
+    /// changed, which covers fading pixels as well as the emulator's damage.
+    pub fn update(
+        &mut self,
+        display: impl IntoIterator<Item = bool>,
+        damage: DisplayDamage,
+    ) -> DisplayDamage {
+        if damage.is_empty() && !self.fading {
+            return DisplayDamage::NONE;
+        }
+        let mut changed = DisplayDamage::NONE;
+        self.fading = false;
+        for (idx, (intensity, lit)) in self.intensity.iter_mut().zip(display).enumerate() {
+            let next = if lit {
+                u8::MAX
+            } else {
+                (u16::from(*intensity) * u16::from(self.decay) / 256) as u8
+            };
+            if next != *intensity {
+                changed.mark(idx % SCREEN_WIDTH, idx / SCREEN_WIDTH);
+                *intensity = next;
+            }
+            self.fading |= next != 0 && next != u8::MAX;
+        }
+        changed
+    }
+
+    /// Brightness of each pixel, row-major
+    #[must_use]
+    pub const fn intensities(&self) -> &[u8] {
+        &self.intensity
+    }
+
+    /// Colour of each pixel, row-major
+    pub fn colors(&self, palette: Palette) -> impl Iterator<Item = Rgb> + '_ {
+        self.intensity.iter().map(move |&i| palette.color(i))
+    }
+
+    /// Fill `out` with 4 bytes of RGBA per pixel, row-major, the layout of canvas `ImageData`
+    ///
+    /// # Panics
+    /// If `out` is not `64 * 32 * 4` bytes long
+    pub fn write_rgba(&self, palette: Palette, out: &mut [u8]) {
+        assert_eq!(out.len(), PIXELS * 4, "an RGBA frame is 64 * 32 * 4 bytes");
+        for (pixel, color) in out.chunks_exact_mut(4).zip(self.colors(palette)) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, u8::MAX]);
+        }
+    }
+}
+
+impl Chip8Emulator {
+    /// The display as a binary PBM image, each pixel a `scale` x `scale` square. Lit pixels are
+    /// white on black, which in PBM means they are the clear bits.
+    #[must_use]
+    pub fn to_pbm(&self, scale: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let (width, height) = (SCREEN_WIDTH * scale, SCREEN_HEIGHT * scale);
+        let mut out = format!("P4\n{width} {height}\n").into_bytes();
+        let mut line = vec![0; width / 8];
+        for row in self.display_rows() {
+            line.fill(0);
+            for x in 0..width {
+                if row & 1 << (SCREEN_WIDTH - 1 - x / scale) == 0 {
+                    line[x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            for _ in 0..scale {
+                out.extend_from_slice(&line);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn palettes_parse_and_mix() {
+        assert_eq!("Amber".parse(), Ok(Palette::AMBER));
+        let custom: Palette = "#102030, 405060".parse().unwrap();
+        assert_eq!(custom.off, Rgb::new(0x10, 0x20, 0x30));
+        assert_eq!(custom.on.to_string(), "#405060");
+        assert_eq!(custom.to_string().parse(), Ok(custom));
+        assert_eq!("pink".parse::<Palette>(), Err(ParsePaletteError));
+        assert_eq!("#12345,#000000".parse::<Palette>(), Err(ParsePaletteError));
+        assert_eq!("+12345,#000000".parse::<Palette>(), Err(ParsePaletteError));
+
+        assert_eq!(Palette::CLASSIC.color(0), Rgb::new(0, 0, 0));
+        assert_eq!(Palette::CLASSIC.color(255), Rgb::new(255, 255, 255));
+        assert_eq!(Palette::LCD.color(128).g, 0x7A);
+    }
+
+    #[test]
+    fn writes_pbm_screenshots() {
+        let mut emulator = Chip8Emulator::new();
+        // LD I, font 0; DRW V0, V0, 5
+        emulator.load_data(&[0xA0, 0x00, 0xD0, 0x05]).unwrap();
+        emulator.run_frame(2);
+
+        let pbm = emulator.to_pbm(1);
+        let (header, pixels) = pbm.split_at(9);
+        assert_eq!(header, b"P4\n64 32\n");
+        assert_eq!(pixels.len(), 64 * 32 / 8);
+        // The top of the "0", then its sides
+        assert_eq!(pixels[..2], [0x0F, 0xFF]);
+        assert_eq!(pixels[8], 0x6F);
+
+        let pbm = emulator.to_pbm(2);
+        let (header, pixels) = pbm.split_at(10);
+        assert_eq!(header, b"P4\n128 64\n");
+        assert_eq!(pixels.len(), 128 * 64 / 8);
+        assert_eq!(pixels[..2], pixels[16..18]);
+        assert_eq!(pixels[..2], [0x00, 0xFF]);
+    }
+
+    #[test]
+    fn phosphor_fades_unlit_pixels() {
+        let mut display = [false; PIXELS];
+        display[0] = true;
+        let mut phosphor = Phosphor::new(Phosphor::PERSISTENCE);
+        let mut damage = DisplayDamage::NONE;
+        damage.mark(0, 0);
+        assert_eq!(phosphor.update(display, damage), damage);
+        assert_eq!(phosphor.intensities()[..2], [255, 0]);
+        assert!(phosphor.update(display, DisplayDamage::NONE).is_empty());
+
+        // Erased for a frame by XOR, then redrawn
+        display[0] = false;
+        phosphor.update(display, damage);
+        assert_eq!(phosphor.intensities()[0], 159);
+        // Still fading without any new damage
+        assert_eq!(phosphor.update(display, DisplayDamage::NONE), damage);
+        assert_eq!(phosphor.intensities()[0], 99);
+        display[0] = true;
+        phosphor.update(display, damage);
+        assert_eq!(phosphor.intensities()[0], 255);
+
+        let mut rgba = [0; PIXELS * 4];
+        phosphor.write_rgba(Palette::GREEN, &mut rgba);
+        assert_eq!(rgba[..8], [0x33, 0xFF, 0x66, 0xFF, 0x00, 0x14, 0x00, 0xFF]);
+
+        // Without decay the display is followed exactly
+        let mut sharp = Phosphor::default();
+        sharp.update(display, DisplayDamage::FULL);
+        display[0] = false;
+        sharp.update(display, damage);
+        assert_eq!(sharp.intensities()[0], 0);
+    }
+}
@@ -0,0 +1,70 @@
+//! Identification of ROM images.
+
+use core::{fmt, str::FromStr};
+
+/// SHA-1 digest of a ROM image, matching the hashes used by the community chip-8-database
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RomHash([u8; 20]);
+
+impl RomHash {
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        Self(sha1_smol::Sha1::from(data).digest().bytes())
+    }
+
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
+
+impl fmt::Display for RomHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returned when a string is not a 40 character hex digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseRomHashError;
+
+impl fmt::Display for ParseRomHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected a 40 character hex SHA-1 digest")
+    }
+}
+
+impl FromStr for RomHash {
+    type Err = ParseRomHashError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        sha1_smol::Digest::from_str(s)
+            .map(|digest| Self(digest.bytes()))
+            .map_err(|_| ParseRomHashError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn hash_round_trips_through_hex() {
+        let hash = RomHash::of(include_bytes!("../../roms/PONG"));
+        let hex = "b232ef880bd6060fb45fa6effed7edf0ae95670e";
+        assert_eq!(hash.to_string(), hex);
+        assert_eq!(hex.parse(), Ok(hash));
+        assert_eq!("not a hash".parse::<RomHash>(), Err(ParseRomHashError));
+    }
+}
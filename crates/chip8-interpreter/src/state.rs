@@ -0,0 +1,166 @@
+//! Save states: a snapshot of the whole machine that can be restored later.
+//!
+//! File layout, integers little-endian:
+//!
+//! | Field                 | Size                              |
+//! |-----------------------|-----------------------------------|
+//! | magic `C8S\x01`       | 4                                 |
+//! | ROM SHA-1             | 20                                |
+//! | V0-VF                 | 16                                |
+//! | I                     | 2                                 |
+//! | program counter       | 2                                 |
+//! | stack pointer         | 1                                 |
+//! | stack                 | 16 * 2                            |
+//! | delay, sound timers   | 2                                 |
+//! | keypad bitfield       | 2                                 |
+//! | quirks bitfield       | 1                                 |
+//! | seed, RNG state       | 8 + 8                             |
+//! | display bitfield      | 64 * 32 / 8, row-major, MSB first |
+//! | memory                | 4096                              |
+
+use alloc::vec::Vec;
+
+use crate::{
+    Chip8Emulator, Chip8Error, DISPLAY_BYTES, DisplayDamage, NUM_REGS, Quirks, RAM_SIZE, RomHash,
+    STACK_SIZE,
+};
+
+const MAGIC: &[u8; 4] = b"C8S\x01";
+
+impl Chip8Emulator {
+    /// Snapshot everything needed to resume execution with [`Self::load_state`]
+    #[must_use]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(4 + 20 + 4096 + 256 + 128);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(self.rom_hash.as_bytes());
+        out.extend_from_slice(&self.v_registers);
+        out.extend_from_slice(&self.i_register.to_le_bytes());
+        out.extend_from_slice(&self.program_counter.to_le_bytes());
+        out.push(self.stack_pointer as u8);
+        for addr in self.stack {
+            out.extend_from_slice(&addr.to_le_bytes());
+        }
+        out.extend_from_slice(&[self.delay_timer, self.sound_timer]);
+        let keys = self
+            .keyboard
+            .iter()
+            .enumerate()
+            .fold(0u16, |bits, (idx, &pressed)| {
+                bits | u16::from(pressed) << idx
+            });
+        out.extend_from_slice(&keys.to_le_bytes());
+        out.push(self.quirks.to_bits());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        out.extend_from_slice(&self.rng.get_seed().to_le_bytes());
+        out.extend_from_slice(&self.display_bytes());
+        out.extend_from_slice(&self.memory);
+        out
+    }
+
+    /// Resume from a snapshot taken by [`Self::save_state`]
+    ///
+    /// # Errors
+    /// `InvalidState` if the data is not a complete save state, leaving the machine untouched
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        let mut reader = Reader(data);
+        if &reader.take::<4>()? != MAGIC {
+            return Err(Chip8Error::InvalidState);
+        }
+        let rom_hash = RomHash::from_bytes(reader.take()?);
+        let v_registers = reader.take::<NUM_REGS>()?;
+        let i_register = u16::from_le_bytes(reader.take()?);
+        let program_counter = u16::from_le_bytes(reader.take()?);
+        let stack_pointer = usize::from(reader.take::<1>()?[0]);
+        if stack_pointer > STACK_SIZE {
+            return Err(Chip8Error::InvalidState);
+        }
+        let mut stack = [0; STACK_SIZE];
+        for addr in &mut stack {
+            *addr = u16::from_le_bytes(reader.take()?);
+        }
+        let [delay_timer, sound_timer] = reader.take()?;
+        let keys = u16::from_le_bytes(reader.take()?);
+        let quirks = Quirks::from_bits(reader.take::<1>()?[0]);
+        let seed = u64::from_le_bytes(reader.take()?);
+        let rng_state = u64::from_le_bytes(reader.take()?);
+        let packed = reader.take::<DISPLAY_BYTES>()?;
+        let memory = reader.take::<RAM_SIZE>()?;
+        if !reader.0.is_empty() {
+            return Err(Chip8Error::InvalidState);
+        }
+
+        self.rom_hash = rom_hash;
+        self.v_registers = v_registers;
+        self.i_register = i_register;
+        self.program_counter = program_counter;
+        self.stack_pointer = stack_pointer;
+        self.stack = stack;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        for (idx, pressed) in self.keyboard.iter_mut().enumerate() {
+            *pressed = keys & 1 << idx != 0;
+        }
+        self.quirks = quirks;
+        self.seed = seed;
+        self.rng.seed(rng_state);
+        for (row, bytes) in self.display.iter_mut().zip(packed.as_chunks().0) {
+            *row = u64::from_be_bytes(*bytes);
+        }
+        self.damage = DisplayDamage::FULL;
+        self.memory = memory;
+        Ok(())
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(Chip8Error::InvalidState)?;
+        self.0 = rest;
+        Ok(*head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_round_trips() {
+        let mut emulator = Chip8Emulator::new();
+        emulator.set_seed(7);
+        emulator
+            .load_data(include_bytes!("../../roms/MAZE"))
+            .unwrap();
+        emulator.keypress(3, true);
+        for _ in 0..30 {
+            emulator.run_frame(10);
+        }
+        let saved = emulator.save_state();
+
+        let mut restored = Chip8Emulator::new();
+        restored.load_state(&saved).unwrap();
+        assert_eq!(restored.state_hash(), emulator.state_hash());
+        for _ in 0..30 {
+            emulator.run_frame(10);
+            restored.run_frame(10);
+        }
+        assert_eq!(restored.state_hash(), emulator.state_hash());
+
+        restored.take_display_damage();
+        restored.load_state(&saved).unwrap();
+        assert_eq!(restored.take_display_damage(), DisplayDamage::FULL);
+
+        let before = restored.state_hash();
+        assert_eq!(
+            restored.load_state(&saved[..saved.len() - 1]),
+            Err(Chip8Error::InvalidState)
+        );
+        assert_eq!(restored.state_hash(), before);
+    }
+}
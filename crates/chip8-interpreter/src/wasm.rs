@@ -0,0 +1,219 @@
+//! JavaScript bindings generated by `wasm-bindgen`, for frontends on the JS target such as
+//! Lustre where the rustler NIFs are unavailable.
+//!
+//! ```js
+//! const chip8 = new WasmChip8();
+//! chip8.loadRom(new Uint8Array(await rom.arrayBuffer()));
+//! chip8.runFrame();
+//! const pixels = chip8.displayBuffer(); // 64 * 32 bytes, 0 or 1
+//! const packed = chip8.packedDisplay(); // 64 * 32 bits
+//! ```
+
+use alloc::{string::String, string::ToString, vec::Vec};
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    Chip8Emulator, Chip8Error, DisplayDamage, NUM_KEYS, SCREEN_HEIGHT, SCREEN_WIDTH, detect::detect,
+};
+
+/// Instructions per frame until `ipf` is set
+const DEFAULT_IPF: u32 = 10;
+
+#[wasm_bindgen]
+pub struct WasmChip8 {
+    emulator: Chip8Emulator,
+    ipf: u32,
+    // Display as bytes for `displayBuffer`, refreshed where frames drew
+    pixels: Vec<u8>,
+    // Damage copied into `pixels` since `takeDamagedRows`
+    damage: DisplayDamage,
+}
+
+impl From<Chip8Error> for JsValue {
+    fn from(err: Chip8Error) -> Self {
+        JsError::from(err).into()
+    }
+}
+
+impl Default for WasmChip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmChip8 {
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new() -> Self {
+        let mut emulator = Chip8Emulator::new();
+        // fastrand has no entropy source on wasm32-unknown-unknown without extra features
+        emulator.set_seed((js_sys::Math::random() * 2f64.powi(53)) as u64);
+        let mut chip8 = Self {
+            emulator,
+            ipf: DEFAULT_IPF,
+            pixels: alloc::vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            damage: DisplayDamage::NONE,
+        };
+        chip8.refresh_pixels();
+        chip8
+    }
+
+    /// Reset the machine and load a ROM, with quirks detected from its opcodes
+    ///
+    /// # Errors
+    /// If the ROM does not fit in memory
+    #[wasm_bindgen(js_name = loadRom)]
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let mut emulator = Chip8Emulator::new();
+        emulator.set_seed(self.emulator.seed());
+        emulator.load_data(rom)?;
+        emulator.set_quirks(detect(rom).quirks());
+        self.emulator = emulator;
+        self.refresh_pixels();
+        Ok(())
+    }
+
+    /// Run one 60Hz frame, returning `false` once the program has halted
+    ///
+    /// # Errors
+    /// If the program hits an unknown opcode or misuses the stack
+    #[wasm_bindgen(js_name = runFrame)]
+    pub fn run_frame(&mut self) -> Result<bool, Chip8Error> {
+        let running = self.emulator.try_run_frame(self.ipf);
+        self.refresh_pixels();
+        Ok(running?.is_some())
+    }
+
+    /// Instructions executed per frame
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn ipf(&self) -> u32 {
+        self.ipf
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_ipf(&mut self, ipf: u32) {
+        self.ipf = ipf;
+    }
+
+    /// # Errors
+    /// If `key` is not 0-15
+    #[wasm_bindgen(js_name = keyDown)]
+    pub fn key_down(&mut self, key: u8) -> Result<(), Chip8Error> {
+        self.key(key, true)
+    }
+
+    /// # Errors
+    /// If `key` is not 0-15
+    #[wasm_bindgen(js_name = keyUp)]
+    pub fn key_up(&mut self, key: u8) -> Result<(), Chip8Error> {
+        self.key(key, false)
+    }
+
+    /// View over the pixels in wasm memory, one byte per pixel, row-major from the top left.
+    /// The view follows later frames, but must be fetched again after anything that could
+    /// grow wasm memory, such as `loadRom` or `saveState`.
+    #[wasm_bindgen(js_name = displayBuffer)]
+    #[must_use]
+    pub fn display_buffer(&self) -> Uint8Array {
+        // SAFETY: `pixels` is never reallocated, and the view is only valid until memory grows
+        unsafe { Uint8Array::view(&self.pixels) }
+    }
+
+    /// The display a bit per pixel, row-major with the leftmost pixel in the top bit of each
+    /// byte, an eighth of the size of `displayBuffer`
+    #[wasm_bindgen(js_name = packedDisplay)]
+    #[must_use]
+    pub fn packed_display(&self) -> Vec<u8> {
+        self.emulator.display_bytes().to_vec()
+    }
+
+    /// Bitfield of the display rows changed since the last call, row 0 in bit 0, so frames that
+    /// drew nothing can skip redrawing
+    #[wasm_bindgen(js_name = takeDamagedRows)]
+    pub fn take_damaged_rows(&mut self) -> u32 {
+        core::mem::take(&mut self.damage).rows()
+    }
+
+    #[must_use]
+    pub fn width() -> usize {
+        SCREEN_WIDTH
+    }
+
+    #[must_use]
+    pub fn height() -> usize {
+        SCREEN_HEIGHT
+    }
+
+    /// SHA-1 of the loaded ROM as hex
+    #[wasm_bindgen(getter, js_name = romHash)]
+    #[must_use]
+    pub fn rom_hash(&self) -> String {
+        self.emulator.rom_hash().to_string()
+    }
+
+    #[wasm_bindgen(js_name = saveState)]
+    #[must_use]
+    pub fn save_state(&self) -> Vec<u8> {
+        self.emulator.save_state()
+    }
+
+    /// # Errors
+    /// If the data is not a save state
+    #[wasm_bindgen(js_name = loadState)]
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+        self.emulator.load_state(state)?;
+        self.refresh_pixels();
+        Ok(())
+    }
+}
+
+impl WasmChip8 {
+    fn key(&mut self, key: u8, pressed: bool) -> Result<(), Chip8Error> {
+        if usize::from(key) >= NUM_KEYS {
+            return Err(Chip8Error::InvalidKey(key));
+        }
+        self.emulator.keypress(usize::from(key), pressed);
+        Ok(())
+    }
+
+    fn refresh_pixels(&mut self) {
+        let damage = self.emulator.take_display_damage();
+        for y in damage.damaged_rows() {
+            let row = &mut self.pixels[y * SCREEN_WIDTH..(y + 1) * SCREEN_WIDTH];
+            for (x, byte) in row.iter_mut().enumerate() {
+                *byte = u8::from(self.emulator.pixel(x, y));
+            }
+        }
+        self.damage |= damage;
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn runs_pong() {
+        let mut chip8 = WasmChip8::new();
+        chip8.load_rom(include_bytes!("../../roms/PONG")).unwrap();
+        let pixels = chip8.display_buffer();
+        assert!(pixels.to_vec().iter().all(|&pixel| pixel == 0));
+        for _ in 0..10 {
+            assert!(chip8.run_frame().unwrap());
+        }
+        assert!(pixels.to_vec().contains(&1));
+        assert_ne!(chip8.take_damaged_rows(), 0);
+        assert_eq!(chip8.packed_display().len(), 256);
+        assert_eq!(chip8.take_damaged_rows(), 0);
+
+        let state = chip8.save_state();
+        chip8.run_frame().unwrap();
+        chip8.load_state(&state).unwrap();
+        assert_eq!(chip8.save_state(), state);
+        assert_eq!(chip8.key_down(16), Err(Chip8Error::InvalidKey(16)));
+    }
+}
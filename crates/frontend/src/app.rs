@@ -0,0 +1,413 @@
+//! The page: a canvas driven by `requestAnimationFrame`, keyboard and touch input, playback
+//! controls, display colours, save slots, a debugger and ROM selection by picker or drag and drop.
+
+use gloo::events::EventListener;
+use gloo::file::callbacks::{FileReader, read_as_bytes};
+use gloo::render::{AnimationFrame, request_animation_frame};
+use gloo::storage::{LocalStorage, Storage};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::JsCast;
+use web_sys::{DragEvent, File, HtmlCanvasElement, HtmlInputElement, KeyboardEvent};
+use yew::prelude::*;
+
+use crate::{
+    appearance::{APPEARANCE_KEY, Appearance, AppearancePicker},
+    debugger::{Debugger, Snapshot},
+    keypad::Keypad,
+    keys::keypad_index,
+    library::{Choice, RECENT_KEY, RecentRoms, Rom},
+    machine::Machine,
+    picker::RomPicker,
+    saves::{SaveSlot, SaveSlots, storage_key},
+    screen,
+    slots::SlotList,
+};
+
+/// Screen pixels per Chip-8 pixel
+const DEFAULT_ZOOM: u32 = 10;
+
+#[derive(Properties, PartialEq, Eq)]
+pub struct Props {
+    #[prop_or(DEFAULT_ZOOM)]
+    pub zoom: u32,
+}
+
+impl Default for Props {
+    fn default() -> Self {
+        Self { zoom: DEFAULT_ZOOM }
+    }
+}
+
+pub enum Msg {
+    /// A display refresh, with the `requestAnimationFrame` timestamp
+    Frame(f64),
+    Key(usize, bool),
+    TogglePause,
+    Reset,
+    ToggleDebugger,
+    Step,
+    ToggleBreakpoint(u16),
+    /// Overwrite a byte of memory
+    Poke(u16, u8),
+    Save(usize),
+    LoadSlot(usize),
+    Restyle(Appearance),
+    Choose(Choice),
+    /// A file picked or dropped, to be read
+    Open(File),
+    /// The contents of an opened file, or why it could not be read
+    Opened(String, Result<Vec<u8>, String>),
+}
+
+pub struct App {
+    machine: Machine,
+    choice: Choice,
+    recent: RecentRoms,
+    /// Why the last ROM picked could not be run
+    load_error: Option<String>,
+    /// Save slots of the running ROM
+    saves: SaveSlots,
+    /// Why the last save or load of a slot failed
+    save_error: Option<String>,
+    debugging: bool,
+    appearance: Appearance,
+    canvas: NodeRef,
+    // Dropping this aborts reading an opened file
+    reader: Option<FileReader>,
+    // Dropping these cancels the pending animation frame and the key listeners
+    animation_frame: Option<AnimationFrame>,
+    key_listeners: Vec<EventListener>,
+}
+
+impl Component for App {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        let choice = Choice::Bundled(0);
+        let rom = choice
+            .rom(&RecentRoms::default())
+            .expect("a ROM is bundled");
+        let mut machine = Machine::new(rom.data).expect("bundled ROMs fit in memory");
+        let appearance: Appearance = stored(APPEARANCE_KEY);
+        machine.set_decay(appearance.decay());
+        Self {
+            saves: stored(&storage_key(machine.emulator().rom_hash())),
+            machine,
+            choice,
+            recent: stored(RECENT_KEY),
+            load_error: None,
+            save_error: None,
+            debugging: false,
+            appearance,
+            canvas: NodeRef::default(),
+            reader: None,
+            animation_frame: None,
+            key_listeners: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Frame(now) => {
+                self.request_frame(ctx);
+                let was_running = self.machine.fault().is_none();
+                let was_paused = self.machine.paused();
+                let polled = self.machine.polled_keys();
+                let ran = self.machine.advance(now);
+                if ran {
+                    self.draw(ctx);
+                }
+                // Unless the debugger is following along, only a crash, a breakpoint or the
+                // program reading other keys changes the controls
+                (ran && self.debugging)
+                    || (was_running && self.machine.fault().is_some())
+                    || was_paused != self.machine.paused()
+                    || polled != self.machine.polled_keys()
+            }
+            Msg::Key(key, pressed) => self.machine.keypress(key, pressed),
+            Msg::TogglePause => {
+                self.machine.toggle_pause();
+                true
+            }
+            Msg::Reset => {
+                self.machine.reset();
+                self.draw(ctx);
+                true
+            }
+            Msg::ToggleDebugger => {
+                self.debugging = !self.debugging;
+                true
+            }
+            Msg::Step => {
+                self.machine.step();
+                self.draw(ctx);
+                true
+            }
+            Msg::ToggleBreakpoint(addr) => {
+                self.machine.toggle_breakpoint(addr);
+                true
+            }
+            Msg::Poke(addr, value) => {
+                self.machine.poke(addr, value);
+                true
+            }
+            Msg::Save(idx) => {
+                let emulator = self.machine.emulator();
+                self.saves.set(idx, SaveSlot::capture(emulator, now()));
+                self.save_error = LocalStorage::set(storage_key(emulator.rom_hash()), &self.saves)
+                    .err()
+                    .map(|err| format!("Couldn't save slot {}: {err}", idx + 1));
+                true
+            }
+            Msg::LoadSlot(idx) => {
+                if let Some(slot) = self.saves.get(idx) {
+                    self.save_error = self
+                        .machine
+                        .load_state(&slot.state)
+                        .err()
+                        .map(|err| format!("Couldn't load slot {}: {err}", idx + 1));
+                    self.draw(ctx);
+                }
+                true
+            }
+            Msg::Restyle(appearance) => {
+                self.appearance = appearance;
+                self.machine.set_decay(appearance.decay());
+                self.machine.invalidate();
+                // Only a preference, so failing to remember it is not worth reporting
+                let _ = LocalStorage::set(APPEARANCE_KEY, appearance);
+                self.draw(ctx);
+                true
+            }
+            Msg::Choose(choice) => {
+                if let Some(rom) = choice.rom(&self.recent) {
+                    self.start(ctx, choice, &rom);
+                }
+                true
+            }
+            Msg::Open(file) => {
+                let name = file.name();
+                let link = ctx.link().clone();
+                self.reader = Some(read_as_bytes(&file.into(), move |data| {
+                    link.send_message(Msg::Opened(name, data.map_err(|err| err.to_string())));
+                }));
+                false
+            }
+            Msg::Opened(name, data) => {
+                self.reader = None;
+                match data {
+                    Ok(data) => {
+                        let rom = Rom { name, data };
+                        if self.start(ctx, Choice::Recent(0), &rom) {
+                            self.recent.remember(rom);
+                            // Quota errors only cost the ROM its place in the list
+                            let _ = LocalStorage::set(RECENT_KEY, &self.recent);
+                        }
+                    }
+                    Err(err) => self.load_error = Some(format!("Couldn't read {name}: {err}")),
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let (width, height) = screen::canvas_size(ctx.props().zoom);
+        let link = ctx.link();
+        let ondragover = Callback::from(|event: DragEvent| event.prevent_default());
+        let ondrop = link.batch_callback(|event: DragEvent| {
+            event.prevent_default();
+            event
+                .data_transfer()
+                .and_then(|transfer| transfer.files())
+                .and_then(|files| files.get(0))
+                .map(Msg::Open)
+        });
+        let recent: Vec<AttrValue> = self
+            .recent
+            .iter()
+            .map(|rom| AttrValue::from(rom.name.clone()))
+            .collect();
+        html! {
+            <main class="chip8" {ondragover} {ondrop}>
+                <RomPicker
+                    {recent}
+                    selected={self.choice}
+                    on_choose={link.callback(Msg::Choose)}
+                    on_open={link.callback(Msg::Open)}
+                />
+                <canvas ref={self.canvas.clone()} width={width.to_string()} height={height.to_string()} />
+                <Keypad
+                    held={self.machine.held_keys()}
+                    polled={self.machine.polled_keys()}
+                    on_key={link.callback(|(key, pressed)| Msg::Key(key, pressed))}
+                />
+                <p class="hint">{ "Drop a ROM file anywhere to play it" }</p>
+                <div class="controls">
+                    <button onclick={link.callback(|_| Msg::TogglePause)}>
+                        { if self.machine.paused() { "Resume" } else { "Pause" } }
+                    </button>
+                    <button onclick={link.callback(|_| Msg::Reset)}>{ "Reset" }</button>
+                    <button onclick={link.callback(|_| Msg::ToggleDebugger)}>
+                        { if self.debugging { "Hide debugger" } else { "Debug" } }
+                    </button>
+                </div>
+                <AppearancePicker
+                    appearance={self.appearance}
+                    on_change={link.callback(Msg::Restyle)}
+                />
+                if self.debugging {
+                    <Debugger
+                        snapshot={Snapshot::of(self.machine.emulator())}
+                        breakpoints={self.machine.breakpoints().clone()}
+                        paused={self.machine.paused() && self.machine.fault().is_none()}
+                        on_step={link.callback(|()| Msg::Step)}
+                        on_continue={link.callback(|()| Msg::TogglePause)}
+                        on_toggle_breakpoint={link.callback(Msg::ToggleBreakpoint)}
+                        on_poke={link.callback(|(addr, value)| Msg::Poke(addr, value))}
+                    />
+                }
+                <SlotList
+                    slots={self.saves.clone()}
+                    on_save={link.callback(Msg::Save)}
+                    on_load={link.callback(Msg::LoadSlot)}
+                />
+                if let Some(error) = &self.load_error {
+                    <p class="fault">{ error }</p>
+                }
+                if let Some(error) = &self.save_error {
+                    <p class="fault">{ error }</p>
+                }
+                if let Some(fault) = self.machine.fault() {
+                    <p class="fault">{ format!("Crashed: {fault}") }</p>
+                }
+            </main>
+        }
+    }
+
+    fn rendered(&mut self, ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            self.listen_for_keys(ctx);
+            self.draw(ctx);
+            self.request_frame(ctx);
+        }
+    }
+}
+
+impl App {
+    /// Replace the running machine, unless the ROM cannot be loaded. Returns whether it could.
+    fn start(&mut self, ctx: &Context<Self>, choice: Choice, rom: &Rom) -> bool {
+        match Machine::new(rom.data.clone()) {
+            Ok(mut machine) => {
+                machine.set_decay(self.appearance.decay());
+                self.saves = stored(&storage_key(machine.emulator().rom_hash()));
+                self.save_error = None;
+                self.machine = machine;
+                self.choice = choice;
+                self.load_error = None;
+                self.draw(ctx);
+                true
+            }
+            Err(err) => {
+                self.load_error = Some(format!("Couldn't load {}: {err}", rom.name));
+                false
+            }
+        }
+    }
+
+    fn request_frame(&mut self, ctx: &Context<Self>) {
+        let link = ctx.link().clone();
+        self.animation_frame = Some(request_animation_frame(move |now| {
+            link.send_message(Msg::Frame(now));
+        }));
+    }
+
+    fn listen_for_keys(&mut self, ctx: &Context<Self>) {
+        let window = gloo::utils::window();
+        self.key_listeners = [("keydown", true), ("keyup", false)]
+            .into_iter()
+            .map(|(event, pressed)| {
+                let link = ctx.link().clone();
+                EventListener::new(&window, event, move |event| {
+                    let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+                        return;
+                    };
+                    // Typing into the debugger's memory view
+                    if event
+                        .target()
+                        .is_some_and(|target| target.has_type::<HtmlInputElement>())
+                    {
+                        return;
+                    }
+                    if let Some(key) = keypad_index(&event.code()) {
+                        event.prevent_default();
+                        link.send_message(Msg::Key(key, pressed));
+                    }
+                })
+            })
+            .collect();
+    }
+
+    /// Paint the parts of the display that changed since the last draw
+    fn draw(&mut self, ctx: &Context<Self>) {
+        if let Some(canvas) = self.canvas.cast::<HtmlCanvasElement>() {
+            let damage = self.machine.take_damage();
+            screen::draw(
+                &canvas,
+                self.machine.phosphor(),
+                self.appearance.palette,
+                ctx.props().zoom,
+                damage,
+            );
+        }
+    }
+}
+
+/// What earlier visits left in local storage under `key`. Rendering on the server has no storage
+/// to read.
+fn stored<T: Default + DeserializeOwned>(key: &str) -> T {
+    if cfg!(target_arch = "wasm32") {
+        LocalStorage::get(key).unwrap_or_default()
+    } else {
+        T::default()
+    }
+}
+
+/// Milliseconds since the Unix epoch
+fn now() -> f64 {
+    if cfg!(target_arch = "wasm32") {
+        js_sys::Date::now()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn renders_scaled_canvas_and_controls() {
+        let html = yew::ServerRenderer::<App>::with_props(|| Props { zoom: 4 })
+            .hydratable(false)
+            .render()
+            .await;
+        assert!(
+            html.contains(r#"<canvas width="256" height="128">"#),
+            "{html}"
+        );
+        assert!(html.contains(">Pause</button>"), "{html}");
+        assert!(html.contains(">Reset</button>"), "{html}");
+        assert!(html.contains(">Debug</button>"), "{html}");
+        assert!(html.contains(">Phosphor</label>"), "{html}");
+        assert_eq!(html.matches(">Save</button>").count(), 4, "{html}");
+        assert!(!html.contains("debugger"), "{html}");
+        for name in ["PONG", "GUESS", "MAZE"] {
+            assert!(html.contains(&format!(">{name}</option>")), "{html}");
+        }
+        assert!(html.contains(r#"<input type="file">"#), "{html}");
+        assert_eq!(html.matches(r#"<button class="key"#).count(), 16, "{html}");
+        assert!(!html.contains("Crashed"), "{html}");
+    }
+}
@@ -0,0 +1,178 @@
+//! How the display looks: a palette and whether pixels fade like a CRT, remembered between visits.
+
+use chip8_interpreter::render::{Palette, Phosphor, Rgb};
+use serde::{Deserialize, Serialize};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+/// Local storage key for the [`Appearance`]
+pub const APPEARANCE_KEY: &str = "chip8.appearance";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Appearance {
+    #[serde(with = "palette")]
+    pub palette: Palette,
+    pub phosphor: bool,
+}
+
+impl Appearance {
+    /// How much brightness pixels keep per frame once unlit, see [`Phosphor::new`]
+    pub const fn decay(self) -> u8 {
+        if self.phosphor {
+            Phosphor::PERSISTENCE
+        } else {
+            0
+        }
+    }
+
+    /// Name of the palette in [`Palette::NAMED`], if it is one of those
+    fn palette_name(self) -> Option<&'static str> {
+        Palette::NAMED
+            .iter()
+            .find(|(_, palette)| *palette == self.palette)
+            .map(|(name, _)| *name)
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub appearance: Appearance,
+    pub on_change: Callback<Appearance>,
+}
+
+#[function_component(AppearancePicker)]
+pub fn appearance_picker(props: &Props) -> Html {
+    let appearance = props.appearance;
+    let named = appearance.palette_name();
+    let on_palette = props.on_change.reform(move |event: Event| {
+        let select: HtmlSelectElement = event.target_unchecked_into();
+        let palette = select.value().parse().unwrap_or(appearance.palette);
+        Appearance {
+            palette,
+            ..appearance
+        }
+    });
+    let on_color = |on: bool| {
+        props.on_change.reform(move |event: Event| {
+            let input: HtmlInputElement = event.target_unchecked_into();
+            let mut palette = appearance.palette;
+            if let Ok(color) = input.value().parse::<Rgb>() {
+                *(if on {
+                    &mut palette.on
+                } else {
+                    &mut palette.off
+                }) = color;
+            }
+            Appearance {
+                palette,
+                ..appearance
+            }
+        })
+    };
+    let on_phosphor = props.on_change.reform(move |event: Event| {
+        let input: HtmlInputElement = event.target_unchecked_into();
+        Appearance {
+            phosphor: input.checked(),
+            ..appearance
+        }
+    });
+    html! {
+        <div class="appearance">
+            <select onchange={on_palette}>
+                { for Palette::NAMED.iter().map(|(name, _)| html! {
+                    <option value={*name} selected={named == Some(*name)}>{ name }</option>
+                }) }
+                // Shown for colours picked below
+                <option disabled=true selected={named.is_none()}>{ "custom" }</option>
+            </select>
+            <input type="color" title="Unlit" value={appearance.palette.off.to_string()} onchange={on_color(false)} />
+            <input type="color" title="Lit" value={appearance.palette.on.to_string()} onchange={on_color(true)} />
+            <label>
+                <input type="checkbox" checked={appearance.phosphor} onchange={on_phosphor} />
+                { "Phosphor" }
+            </label>
+        </div>
+    }
+}
+
+/// A palette as the `off,on` text it parses from
+mod palette {
+    use chip8_interpreter::render::Palette;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    // `#[serde(with)]` hands over fields by reference, so this cannot take a `Palette` by value
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S: Serializer>(palette: &Palette, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(palette)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Palette, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn render(appearance: Appearance) -> String {
+        yew::ServerRenderer::<AppearancePicker>::with_props(move || Props {
+            appearance,
+            on_change: Callback::noop(),
+        })
+        .hydratable(false)
+        .render()
+        .await
+    }
+
+    #[tokio::test]
+    async fn offers_named_and_custom_palettes() {
+        let html = render(Appearance {
+            palette: Palette::AMBER,
+            phosphor: true,
+        })
+        .await;
+        assert!(
+            html.contains(r#"<option value="amber" selected="selected">"#),
+            "{html}"
+        );
+        assert!(html.contains(r##"value="#ffb000""##), "{html}");
+        assert!(
+            html.contains(r#"<input checked type="checkbox">"#),
+            "{html}"
+        );
+
+        let custom = Palette::new(Rgb::new(1, 2, 3), Rgb::new(4, 5, 6));
+        let html = render(Appearance {
+            palette: custom,
+            phosphor: false,
+        })
+        .await;
+        assert!(
+            html.contains(r#"<option disabled="disabled" selected="selected">custom"#),
+            "{html}"
+        );
+        assert!(html.contains(r##"value="#040506""##), "{html}");
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let appearance = Appearance {
+            palette: Palette::LCD,
+            phosphor: true,
+        };
+        let json = serde_json::to_string(&appearance).unwrap();
+        assert_eq!(json, r##"{"palette":"#9bbc0f,#0f380f","phosphor":true}"##);
+        assert_eq!(
+            serde_json::from_str::<Appearance>(&json).unwrap(),
+            appearance
+        );
+        assert_eq!(
+            serde_json::from_str::<Appearance>("{}").unwrap(),
+            Appearance::default()
+        );
+    }
+}
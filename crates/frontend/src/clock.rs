@@ -0,0 +1,55 @@
+//! Fixed-rate scheduling of emulator frames from `requestAnimationFrame` timestamps.
+
+/// Emulator frames per second
+const FRAME_RATE: f64 = 60.0;
+
+/// Most frames run for one animation frame, so a tab returning from the background does not
+/// fast-forward through everything it missed
+const MAX_CATCH_UP: u32 = 4;
+
+/// Converts display refreshes, whatever their rate, into a steady 60 emulator frames a second
+#[derive(Debug, Default)]
+pub struct FrameClock {
+    start: Option<f64>,
+    // Frames the clock has accounted for since `start`, run or dropped
+    frames: u64,
+}
+
+impl FrameClock {
+    /// Emulator frames due at `now`, a timestamp in milliseconds
+    pub fn advance(&mut self, now: f64) -> u32 {
+        let start = *self.start.get_or_insert(now);
+        // Clamped to zero and floored, and a u64 of frames outlasts any page
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let target = ((now - start).max(0.0) * FRAME_RATE / 1000.0).floor() as u64;
+        let due = target.saturating_sub(self.frames);
+        self.frames = self.frames.max(target);
+        u32::try_from(due).map_or(MAX_CATCH_UP, |due| due.min(MAX_CATCH_UP))
+    }
+
+    /// Forget elapsed time, so resuming after a pause does not catch up
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_sixty_frames_a_second() {
+        let mut clock = FrameClock::default();
+        assert_eq!(clock.advance(1000.0), 0);
+        // A 144Hz display
+        let frames: u32 = (1..=144)
+            .map(|refresh| clock.advance(1000.0 + f64::from(refresh) * 1000.0 / 144.0))
+            .sum();
+        assert_eq!(frames, 60);
+        assert_eq!(clock.advance(60_000.0), MAX_CATCH_UP);
+        assert_eq!(clock.advance(60_010.0), 0);
+
+        clock.reset();
+        assert_eq!(clock.advance(90_000.0), 0);
+    }
+}
@@ -0,0 +1,267 @@
+//! Debugger panel: registers, stack, code around the program counter, an editable view of
+//! memory, and stepping and breakpoint controls.
+
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use chip8_interpreter::{Chip8Emulator, disasm::Instruction};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// Instructions listed before and after the program counter
+const LISTING_BEFORE: u16 = 6;
+const LISTING_AFTER: u16 = 12;
+
+/// Bytes in a page of the memory view, shown 16 to a row
+const PAGE_SIZE: usize = 256;
+
+/// The machine state the panel shows, copied so it can be compared between renders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub registers: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub stack: Vec<u16>,
+    pub memory: Rc<[u8]>,
+}
+
+impl Snapshot {
+    pub fn of(emulator: &Chip8Emulator) -> Self {
+        Self {
+            registers: *emulator.registers(),
+            i: emulator.i_register(),
+            pc: emulator.program_counter(),
+            delay_timer: emulator.delay_timer(),
+            sound_timer: emulator.sound_timer(),
+            stack: emulator.stack().to_vec(),
+            memory: emulator.memory().into(),
+        }
+    }
+}
+
+/// Addresses and opcodes of the instructions around `pc`, aligned with it
+pub fn listing(memory: &[u8], pc: u16) -> impl Iterator<Item = (u16, u16)> + '_ {
+    let first = pc - (pc / 2).min(LISTING_BEFORE) * 2;
+    (0..LISTING_BEFORE + LISTING_AFTER)
+        .map_while(move |idx| first.checked_add(idx * 2))
+        .map_while(|addr| {
+            let bytes = memory.get(usize::from(addr)..usize::from(addr) + 2)?;
+            Some((addr, u16::from_be_bytes([bytes[0], bytes[1]])))
+        })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub snapshot: Snapshot,
+    pub breakpoints: BTreeSet<u16>,
+    pub paused: bool,
+    pub on_step: Callback<()>,
+    pub on_continue: Callback<()>,
+    pub on_toggle_breakpoint: Callback<u16>,
+    /// Address and new value of a byte edited in the memory view
+    pub on_poke: Callback<(u16, u8)>,
+}
+
+#[function_component(Debugger)]
+pub fn debugger(props: &Props) -> Html {
+    let Snapshot {
+        registers,
+        i,
+        pc,
+        delay_timer,
+        sound_timer,
+        stack,
+        memory,
+    } = &props.snapshot;
+    html! {
+        <section class="debugger">
+            <div class="debug-controls">
+                <button disabled={!props.paused} onclick={props.on_step.reform(|_| ())}>{ "Step" }</button>
+                <button disabled={!props.paused} onclick={props.on_continue.reform(|_| ())}>{ "Continue" }</button>
+            </div>
+            <table class="registers">
+                <tr>
+                    { for registers.iter().enumerate().map(|(x, value)| html! {
+                        <td><span class="name">{ format!("V{x:X}") }</span>{ format!("{value:02X}") }</td>
+                    }) }
+                </tr>
+                <tr>
+                    <td><span class="name">{ "I" }</span>{ format!("{i:03X}") }</td>
+                    <td><span class="name">{ "PC" }</span>{ format!("{pc:03X}") }</td>
+                    <td><span class="name">{ "SP" }</span>{ stack.len() }</td>
+                    <td><span class="name">{ "DT" }</span>{ format!("{delay_timer:02X}") }</td>
+                    <td><span class="name">{ "ST" }</span>{ format!("{sound_timer:02X}") }</td>
+                </tr>
+            </table>
+            <div class="panes">
+                <ol class="stack">
+                    if stack.is_empty() {
+                        <li class="empty">{ "Stack empty" }</li>
+                    }
+                    { for stack.iter().rev().map(|addr| html! { <li>{ format!("{addr:03X}") }</li> }) }
+                </ol>
+                <ol class="listing">
+                    { for listing(memory, *pc).map(|(addr, opcode)| {
+                        let class = classes!(
+                            (addr == *pc).then_some("pc"),
+                            props.breakpoints.contains(&addr).then_some("breakpoint"),
+                        );
+                        let onclick = props.on_toggle_breakpoint.reform(move |_| addr);
+                        html! {
+                            <li {class} {onclick} title="Toggle breakpoint">
+                                { format!("{addr:03X}  {opcode:04X}  {}", Instruction::decode(opcode)) }
+                            </li>
+                        }
+                    }) }
+                </ol>
+            </div>
+            <MemoryView memory={memory.clone()} pc={*pc} i={*i} on_poke={props.on_poke.clone()} />
+        </section>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct MemoryProps {
+    memory: Rc<[u8]>,
+    pc: u16,
+    i: u16,
+    on_poke: Callback<(u16, u8)>,
+}
+
+/// A page of memory in hex. Clicking a byte edits it.
+#[function_component(MemoryView)]
+fn memory_view(props: &MemoryProps) -> Html {
+    let page = use_state(|| 0x200 / PAGE_SIZE);
+    let editing = use_state(|| None::<u16>);
+    let input = use_node_ref();
+    {
+        let input = input.clone();
+        use_effect_with(*editing, move |_| {
+            if let Some(input) = input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+                input.select();
+            }
+        });
+    }
+
+    let pages = props.memory.len() / PAGE_SIZE;
+    let go = |target: usize| {
+        let page = page.clone();
+        Callback::from(move |_| page.set(target))
+    };
+    let start = *page * PAGE_SIZE;
+    let rows = props.memory[start..start + PAGE_SIZE]
+        .chunks(16)
+        .enumerate();
+    let byte = |addr: u16, value: u8| {
+        if *editing == Some(addr) {
+            let on_poke = props.on_poke.clone();
+            let done = editing.clone();
+            let onchange = Callback::from(move |event: Event| {
+                let input: HtmlInputElement = event.target_unchecked_into();
+                if let Ok(value) = u8::from_str_radix(input.value().trim(), 16) {
+                    on_poke.emit((addr, value));
+                }
+                done.set(None);
+            });
+            let done = editing.clone();
+            let onblur = Callback::from(move |_| done.set(None));
+            return html! {
+                <input ref={input.clone()} class="byte" value={format!("{value:02X}")} maxlength="2" size="2" {onchange} {onblur} />
+            };
+        }
+        let class = classes!(
+            "byte",
+            (addr == props.pc || addr == props.pc.wrapping_add(1)).then_some("pc"),
+            (addr == props.i).then_some("i"),
+        );
+        let edit = editing.clone();
+        html! {
+            <span {class} onclick={Callback::from(move |_| edit.set(Some(addr)))}>{ format!("{value:02X}") }</span>
+        }
+    };
+    html! {
+        <div class="memory">
+            <div class="memory-nav">
+                <button disabled={*page == 0} onclick={go(page.saturating_sub(1))}>{ "◀" }</button>
+                <span>{ format!("{start:03X}–{:03X}", start + PAGE_SIZE - 1) }</span>
+                <button disabled={*page + 1 == pages} onclick={go(*page + 1)}>{ "▶" }</button>
+                <button onclick={go(usize::from(props.pc) / PAGE_SIZE % pages)}>{ "PC" }</button>
+                <button onclick={go(usize::from(props.i) / PAGE_SIZE % pages)}>{ "I" }</button>
+            </div>
+            { for rows.map(|(row, values)| {
+                let row_start = start + row * 16;
+                html! {
+                    <div class="row">
+                        <span class="addr">{ format!("{row_start:03X}") }</span>
+                        { for values.iter().enumerate().map(|(col, &value)| {
+                            byte(u16::try_from(row_start + col).expect("address in memory"), value)
+                        }) }
+                    </div>
+                }
+            }) }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_follows_pc() {
+        let memory: Vec<u8> = (0..=255).collect();
+        let addrs: Vec<u16> = listing(&memory, 0x20).map(|(addr, _)| addr).collect();
+        assert_eq!(addrs.first(), Some(&0x14));
+        assert_eq!(addrs.len(), 18);
+        // Clamped at both ends of memory, and aligned with an odd PC
+        let near_start: Vec<_> = listing(&memory, 3).take(2).collect();
+        assert_eq!(near_start, [(1, 0x0102), (3, 0x0304)]);
+        assert_eq!(listing(&memory, 0xFC).last(), Some((0xFE, 0xFEFF)));
+        assert_eq!(listing(&memory, u16::MAX).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn renders_machine_state() {
+        let html = yew::ServerRenderer::<Debugger>::with_props(|| {
+            let mut emulator = Chip8Emulator::new();
+            // CALL 0x204; HALT; LD V3, 0x42
+            emulator
+                .load_data(&[0x22, 0x04, 0x00, 0x00, 0x63, 0x42])
+                .unwrap();
+            emulator.tick();
+            Props {
+                snapshot: Snapshot::of(&emulator),
+                breakpoints: BTreeSet::from([0x200]),
+                paused: true,
+                on_step: Callback::noop(),
+                on_continue: Callback::noop(),
+                on_toggle_breakpoint: Callback::noop(),
+                on_poke: Callback::noop(),
+            }
+        })
+        .hydratable(false)
+        .render()
+        .await;
+        assert!(html.contains("<li>202</li>"), "{html}");
+        assert!(
+            html.contains(
+                r#"<li title="Toggle breakpoint" class="breakpoint">200  2204  CALL 0x204</li>"#
+            ),
+            "{html}"
+        );
+        assert!(
+            html.contains(
+                r#"<li title="Toggle breakpoint" class="pc">204  6342  LD V3, 0x42</li>"#
+            ),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<span class="byte pc">63</span>"#),
+            "{html}"
+        );
+        assert!(html.contains("200–2FF"), "{html}");
+    }
+}
@@ -0,0 +1,83 @@
+//! On-screen hex keypad for touch screens, in the COSMAC VIP layout.
+
+use yew::prelude::*;
+
+/// Keys as laid out on the COSMAC VIP, row by row
+pub const LAYOUT: [[usize; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Keys shown pressed, as a bitfield with key 0 in bit 0
+    pub held: u16,
+    /// Keys the program is reading, as a bitfield with key 0 in bit 0
+    pub polled: u16,
+    /// Key and whether it went down or up
+    pub on_key: Callback<(usize, bool)>,
+}
+
+#[function_component(Keypad)]
+pub fn keypad(props: &Props) -> Html {
+    let key = |key: usize| {
+        // Each pointer only reaches the button it touched, so several fingers press several keys
+        let onpointerdown = props.on_key.reform(move |event: PointerEvent| {
+            event.prevent_default();
+            (key, true)
+        });
+        let release = props.on_key.reform(move |_: PointerEvent| (key, false));
+        let class = classes!(
+            "key",
+            (props.held & 1 << key != 0).then_some("held"),
+            (props.polled & 1 << key != 0).then_some("polled"),
+        );
+        html! {
+            <button
+                {class}
+                {onpointerdown}
+                onpointerup={release.clone()}
+                onpointercancel={release.clone()}
+                onpointerleave={release}
+                oncontextmenu={Callback::from(|event: MouseEvent| event.prevent_default())}
+            >
+                { format!("{key:X}") }
+            </button>
+        }
+    };
+    html! {
+        <div class="keypad">
+            { for LAYOUT.iter().flatten().map(|&idx| key(idx)) }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn renders_cosmac_layout_with_highlights() {
+        let html = yew::ServerRenderer::<Keypad>::with_props(|| Props {
+            held: 1 << 0xC,
+            polled: 1 << 1 | 1 << 0xC,
+            on_key: Callback::noop(),
+        })
+        .hydratable(false)
+        .render()
+        .await;
+        let labels: String = html
+            .split("</button>")
+            .filter_map(|button| button.rsplit('>').next())
+            .collect();
+        assert_eq!(labels, "123C456D789EA0BF");
+        assert!(html.contains(r#"<button class="key polled">1<"#), "{html}");
+        assert!(
+            html.contains(r#"<button class="key held polled">C<"#),
+            "{html}"
+        );
+        assert!(html.contains(r#"<button class="key">2<"#), "{html}");
+    }
+}
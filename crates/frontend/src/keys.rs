@@ -0,0 +1,48 @@
+//! Host keyboard to hex keypad mapping.
+
+/// Keypad index for a `KeyboardEvent.code`, laying the 4x4 COSMAC keypad over the left of a
+/// QWERTY keyboard:
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// Q W E R  ->  4 5 6 D
+/// A S D F      7 8 9 E
+/// Z X C V      A 0 B F
+/// ```
+///
+/// Physical key codes are used so the layout stays in place on AZERTY and other layouts.
+pub fn keypad_index(code: &str) -> Option<usize> {
+    let key = match code {
+        "Digit1" => 0x1,
+        "Digit2" => 0x2,
+        "Digit3" => 0x3,
+        "Digit4" => 0xC,
+        "KeyQ" => 0x4,
+        "KeyW" => 0x5,
+        "KeyE" => 0x6,
+        "KeyR" => 0xD,
+        "KeyA" => 0x7,
+        "KeyS" => 0x8,
+        "KeyD" => 0x9,
+        "KeyF" => 0xE,
+        "KeyZ" => 0xA,
+        "KeyX" => 0x0,
+        "KeyC" => 0xB,
+        "KeyV" => 0xF,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_cosmac_layout() {
+        assert_eq!(keypad_index("Digit4"), Some(0xC));
+        assert_eq!(keypad_index("KeyX"), Some(0x0));
+        assert_eq!(keypad_index("KeyV"), Some(0xF));
+        assert_eq!(keypad_index("Space"), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! ROMs the page can switch between: those bundled with it and files opened recently.
+
+use serde::{Deserialize, Serialize};
+
+pub const BUNDLED: [(&str, &[u8]); 3] = [
+    ("PONG", include_bytes!("../../roms/PONG")),
+    ("GUESS", include_bytes!("../../roms/GUESS")),
+    ("MAZE", include_bytes!("../../roms/MAZE")),
+];
+
+/// Local storage key for [`RecentRoms`]
+pub const RECENT_KEY: &str = "chip8.recent";
+
+/// Opened files kept for quick switching, small enough to stay well inside storage quotas
+const MAX_RECENT: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rom {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl Rom {
+    pub fn bundled(idx: usize) -> Option<Self> {
+        let (name, data) = BUNDLED.get(idx)?;
+        Some(Self {
+            name: (*name).to_string(),
+            data: data.to_vec(),
+        })
+    }
+}
+
+/// ROMs opened from disk, most recent first
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecentRoms(Vec<Rom>);
+
+impl RecentRoms {
+    /// Move `rom` to the front, forgetting the oldest entry when full
+    pub fn remember(&mut self, rom: Rom) {
+        self.0.retain(|recent| recent.data != rom.data);
+        self.0.insert(0, rom);
+        self.0.truncate(MAX_RECENT);
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&Rom> {
+        self.0.get(idx)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rom> {
+        self.0.iter()
+    }
+}
+
+/// An entry of the ROM selector, round-tripped through `<option value>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Bundled(usize),
+    Recent(usize),
+}
+
+impl Choice {
+    pub fn value(self) -> String {
+        match self {
+            Self::Bundled(idx) => format!("bundled:{idx}"),
+            Self::Recent(idx) => format!("recent:{idx}"),
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, idx) = value.split_once(':')?;
+        let idx = idx.parse().ok()?;
+        match kind {
+            "bundled" => Some(Self::Bundled(idx)),
+            "recent" => Some(Self::Recent(idx)),
+            _ => None,
+        }
+    }
+
+    pub fn rom(self, recent: &RecentRoms) -> Option<Rom> {
+        match self {
+            Self::Bundled(idx) => Rom::bundled(idx),
+            Self::Recent(idx) => recent.get(idx).cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(name: &str, data: &[u8]) -> Rom {
+        Rom {
+            name: name.to_string(),
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn remembers_most_recent_first() {
+        let mut recent = RecentRoms::default();
+        for idx in 0..10 {
+            recent.remember(rom(&format!("rom{idx}"), &[idx]));
+        }
+        // Opening a ROM again moves it to the front, even under another name
+        recent.remember(rom("renamed", &[5]));
+        let names: Vec<_> = recent.iter().map(|rom| rom.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "renamed", "rom9", "rom8", "rom7", "rom6", "rom4", "rom3", "rom2"
+            ]
+        );
+
+        let json = serde_json::to_string(&recent).unwrap();
+        assert_eq!(serde_json::from_str::<RecentRoms>(&json).unwrap(), recent);
+    }
+
+    #[test]
+    fn choices_round_trip_through_option_values() {
+        let recent = RecentRoms(vec![rom("mine", &[1, 2])]);
+        for choice in [Choice::Bundled(2), Choice::Recent(0)] {
+            assert_eq!(Choice::parse(&choice.value()), Some(choice));
+        }
+        assert_eq!(Choice::parse("bundled:x"), None);
+        assert_eq!(Choice::Bundled(2).rom(&recent).unwrap().name, "MAZE");
+        assert_eq!(Choice::Recent(0).rom(&recent).unwrap().data, [1, 2]);
+        assert_eq!(Choice::Recent(1).rom(&recent), None);
+    }
+}
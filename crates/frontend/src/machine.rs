@@ -0,0 +1,420 @@
+//! The emulator session the page drives, kept free of browser APIs so it can be tested natively.
+
+use std::collections::BTreeSet;
+
+use chip8_interpreter::{
+    Chip8Emulator, Chip8Error, DisplayDamage, detect::detect, render::Phosphor,
+};
+
+use crate::clock::FrameClock;
+
+/// Instructions executed per 60Hz frame
+const DEFAULT_IPF: u32 = 10;
+
+/// Frames a key stays highlighted after the program checks it, as many programs only read the
+/// keypad every few frames
+const POLL_WINDOW: usize = 8;
+
+pub struct Machine {
+    rom: Vec<u8>,
+    emulator: Chip8Emulator,
+    ipf: u32,
+    clock: FrameClock,
+    paused: bool,
+    fault: Option<Chip8Error>,
+    // Bitfields of keys, key 0 in bit 0
+    held: u16,
+    polls: [u16; POLL_WINDOW],
+    frame: usize,
+    // Instructions run so far in the current frame, which may have stopped at a breakpoint
+    frame_ticks: u32,
+    breakpoints: BTreeSet<u16>,
+    // Brightness of the pixels shown, updated as frames end
+    phosphor: Phosphor,
+    // Where the phosphor changed since `take_damage`
+    damage: DisplayDamage,
+    // Set on resuming, so the instruction a breakpoint stopped on gets to run
+    resuming: bool,
+}
+
+impl Machine {
+    /// Start running a ROM, with quirks detected from its opcodes
+    ///
+    /// # Errors
+    /// If the ROM does not fit in memory
+    pub fn new(rom: Vec<u8>) -> Result<Self, Chip8Error> {
+        let emulator = boot(&rom)?;
+        Ok(Self {
+            rom,
+            emulator,
+            ipf: DEFAULT_IPF,
+            clock: FrameClock::default(),
+            paused: false,
+            fault: None,
+            held: 0,
+            polls: [0; POLL_WINDOW],
+            frame: 0,
+            frame_ticks: 0,
+            breakpoints: BTreeSet::new(),
+            phosphor: Phosphor::default(),
+            damage: DisplayDamage::FULL,
+            resuming: false,
+        })
+    }
+
+    pub const fn emulator(&self) -> &Chip8Emulator {
+        &self.emulator
+    }
+
+    pub const fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Why the program stopped, if it crashed
+    pub const fn fault(&self) -> Option<Chip8Error> {
+        self.fault
+    }
+
+    /// Keys currently pressed, as a bitfield with key 0 in bit 0
+    pub const fn held_keys(&self) -> u16 {
+        self.held
+    }
+
+    /// Keys the program checked during recent frames, as a bitfield with key 0 in bit 0
+    pub fn polled_keys(&self) -> u16 {
+        self.polls.iter().fold(0, |polled, keys| polled | keys)
+    }
+
+    /// Run the frames due at `now`, a timestamp in milliseconds. Returns whether any ran.
+    pub fn advance(&mut self, now: f64) -> bool {
+        if self.paused {
+            return false;
+        }
+        let due = self.clock.advance(now);
+        for _ in 0..due {
+            if !self.run_frame() {
+                break;
+            }
+        }
+        due > 0
+    }
+
+    /// The display to draw
+    pub const fn phosphor(&self) -> &Phosphor {
+        &self.phosphor
+    }
+
+    /// Change how long unlit pixels take to fade, see [`Phosphor::new`]
+    pub const fn set_decay(&mut self, decay: u8) {
+        self.phosphor.set_decay(decay);
+    }
+
+    /// Where the phosphor changed since the last call, and so needs drawing again
+    pub const fn take_damage(&mut self) -> DisplayDamage {
+        std::mem::replace(&mut self.damage, DisplayDamage::NONE)
+    }
+
+    /// Have the next [`Self::take_damage`] cover the whole display, for when the way it is drawn
+    /// changes
+    pub const fn invalidate(&mut self) {
+        self.damage = DisplayDamage::FULL;
+    }
+
+    /// Execute a single instruction while paused
+    pub fn step(&mut self) {
+        if self.paused && self.fault.is_none() {
+            let frame = self.frame;
+            self.tick();
+            // Show what the instruction drew without waiting for the frame to end
+            if self.frame == frame {
+                self.update_phosphor();
+            }
+        }
+    }
+
+    /// Addresses execution pauses at
+    pub const fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    pub fn toggle_breakpoint(&mut self, addr: u16) {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+        }
+    }
+
+    /// Overwrite a byte of memory
+    pub fn poke(&mut self, addr: u16, value: u8) {
+        if let Some(byte) = self.emulator.memory_mut().get_mut(usize::from(addr)) {
+            *byte = value;
+        }
+    }
+
+    /// Press or release a key 0-15. Returns whether that changed anything.
+    pub fn keypress(&mut self, key: usize, pressed: bool) -> bool {
+        let held = if pressed {
+            self.held | 1 << key
+        } else {
+            self.held & !(1 << key)
+        };
+        self.emulator.keypress(key, pressed);
+        let changed = held != self.held;
+        self.held = held;
+        changed
+    }
+
+    /// Pause or resume, unless the program has crashed
+    pub fn toggle_pause(&mut self) {
+        if self.fault.is_none() {
+            self.paused = !self.paused;
+            self.resuming = !self.paused;
+        }
+        self.clock.reset();
+    }
+
+    /// Restart the ROM from power-on, keeping the pause state
+    pub fn reset(&mut self) {
+        let seed = self.emulator.seed();
+        self.emulator = boot(&self.rom).expect("the ROM loaded before");
+        self.emulator.set_seed(seed);
+        self.restarted();
+    }
+
+    /// Continue from a [`Chip8Emulator::save_state`] snapshot, keeping the pause state
+    ///
+    /// # Errors
+    /// If the snapshot is corrupt, leaving the machine as it was
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+        self.emulator.load_state(state)?;
+        self.restarted();
+        Ok(())
+    }
+
+    /// Forget what the old machine state was doing, after replacing it
+    fn restarted(&mut self) {
+        // Fingers still on the keypad stay down
+        for key in 0..16 {
+            self.emulator.keypress(key, self.held & 1 << key != 0);
+        }
+        self.polls = [0; POLL_WINDOW];
+        self.frame_ticks = 0;
+        self.phosphor = Phosphor::new(self.phosphor.decay());
+        self.update_phosphor();
+        self.damage = DisplayDamage::FULL;
+        self.paused &= self.fault.is_none();
+        self.fault = None;
+        self.clock.reset();
+    }
+
+    /// Run the rest of the current frame. Returns false if it stopped at a breakpoint or crashed.
+    fn run_frame(&mut self) -> bool {
+        let frame = self.frame;
+        while self.frame == frame {
+            let pc = self.emulator.program_counter();
+            if !std::mem::take(&mut self.resuming) && self.breakpoints.contains(&pc) {
+                self.paused = true;
+                return false;
+            }
+            if !self.tick() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Execute one instruction, ending the frame after `ipf` of them. Returns false on a crash.
+    fn tick(&mut self) -> bool {
+        match self.emulator.try_tick() {
+            Ok(Some(())) => self.frame_ticks += 1,
+            Ok(None) => {
+                // Stay on the halt instruction, as `Chip8Emulator::run_frame` does
+                let pc = self.emulator.program_counter();
+                self.emulator.set_program_counter(pc - 2);
+                self.frame_ticks = self.ipf;
+            }
+            Err(fault) => {
+                self.fault = Some(fault);
+                self.paused = true;
+                return false;
+            }
+        }
+        if self.frame_ticks >= self.ipf {
+            self.emulator.tick_timers();
+            self.polls[self.frame % POLL_WINDOW] = self.emulator.take_polled_keys();
+            self.update_phosphor();
+            self.frame += 1;
+            self.frame_ticks = 0;
+        }
+        true
+    }
+
+    fn update_phosphor(&mut self) {
+        let damage = self.emulator.take_display_damage();
+        self.damage |= self.phosphor.update(self.emulator.get_display(), damage);
+    }
+}
+
+fn boot(rom: &[u8]) -> Result<Chip8Emulator, Chip8Error> {
+    let mut emulator = Chip8Emulator::new();
+    emulator.load_data(rom)?;
+    emulator.set_quirks(detect(rom).quirks());
+    Ok(emulator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PONG: &[u8] = include_bytes!("../../roms/PONG");
+
+    fn lit(machine: &Machine) -> usize {
+        machine.emulator().get_display().filter(|&p| p).count()
+    }
+
+    #[test]
+    fn runs_pauses_and_resets() {
+        let mut machine = Machine::new(PONG.to_vec()).unwrap();
+        assert!(!machine.advance(0.0));
+        assert!(machine.advance(50.0));
+        assert!(lit(&machine) > 0);
+
+        machine.toggle_pause();
+        let state = machine.emulator().state_hash();
+        assert!(!machine.advance(1000.0));
+        assert_eq!(machine.emulator().state_hash(), state);
+
+        machine.reset();
+        assert!(machine.paused());
+        assert_eq!(lit(&machine), 0);
+    }
+
+    #[test]
+    fn phosphor_follows_frames() {
+        let glowing = |machine: &Machine| {
+            machine
+                .phosphor()
+                .intensities()
+                .iter()
+                .filter(|&&i| i > 0)
+                .count()
+        };
+        let mut machine = Machine::new(PONG.to_vec()).unwrap();
+        machine.advance(0.0);
+        machine.advance(50.0);
+        assert_eq!(glowing(&machine), lit(&machine));
+
+        // Once play starts, the ball leaves a fading trail
+        machine.set_decay(Phosphor::PERSISTENCE);
+        for frame in 4..180 {
+            machine.advance(f64::from(frame) * 1000.0 / 60.0);
+        }
+        assert!(glowing(&machine) > lit(&machine));
+
+        machine.reset();
+        assert_eq!(glowing(&machine), 0);
+        assert_eq!(machine.phosphor().decay(), Phosphor::PERSISTENCE);
+    }
+
+    #[test]
+    fn reports_damage_to_redraw() {
+        let mut machine = Machine::new(PONG.to_vec()).unwrap();
+        assert_eq!(machine.take_damage(), DisplayDamage::FULL);
+        assert!(machine.take_damage().is_empty());
+        machine.advance(0.0);
+        machine.advance(50.0);
+        assert!(!machine.take_damage().is_empty());
+
+        // Nothing runs while paused
+        machine.toggle_pause();
+        machine.advance(100.0);
+        assert!(machine.take_damage().is_empty());
+        machine.invalidate();
+        assert_eq!(machine.take_damage(), DisplayDamage::FULL);
+        machine.reset();
+        assert_eq!(machine.take_damage(), DisplayDamage::FULL);
+    }
+
+    #[test]
+    fn faults_pause_until_reset() {
+        // RET with nothing on the stack
+        let mut machine = Machine::new(vec![0x00, 0xEE]).unwrap();
+        machine.advance(0.0);
+        machine.advance(20.0);
+        assert_eq!(machine.fault(), Some(Chip8Error::StackUnderflow));
+        assert!(machine.paused());
+        machine.toggle_pause();
+        assert!(machine.paused());
+
+        machine.reset();
+        assert_eq!(machine.fault(), None);
+        assert!(!machine.paused());
+        assert!(Machine::new(vec![0; 4096]).is_err());
+    }
+
+    #[test]
+    fn loads_saved_states() {
+        let mut machine = Machine::new(PONG.to_vec()).unwrap();
+        let state = machine.emulator().save_state();
+        machine.keypress(1, true);
+        machine.advance(0.0);
+        machine.advance(50.0);
+        assert!(lit(&machine) > 0);
+
+        assert!(machine.load_state(&state[1..]).is_err());
+        assert!(lit(&machine) > 0);
+        machine.load_state(&state).unwrap();
+        assert_eq!(lit(&machine), 0);
+        assert_eq!(machine.held_keys(), 1 << 1);
+    }
+
+    #[test]
+    fn tracks_held_and_polled_keys() {
+        let mut machine = Machine::new(PONG.to_vec()).unwrap();
+        assert!(machine.keypress(1, true));
+        assert!(!machine.keypress(1, true));
+        assert!(machine.keypress(0xC, true));
+        assert!(machine.keypress(1, false));
+        assert_eq!(machine.held_keys(), 1 << 0xC);
+
+        for frame in 0..120 {
+            machine.advance(f64::from(frame) * 1000.0 / 60.0);
+        }
+        // Once the opening score has been shown, paddles move with 1/4 and C/D
+        assert_eq!(machine.polled_keys(), 1 << 1 | 1 << 4 | 1 << 0xC | 1 << 0xD);
+        machine.reset();
+        assert_eq!(machine.polled_keys(), 0);
+    }
+
+    #[test]
+    fn stops_at_breakpoints_and_steps() {
+        // LD V0, 1; ADD V0, 1; JP 0x202
+        let mut machine = Machine::new(vec![0x60, 0x01, 0x70, 0x01, 0x12, 0x02]).unwrap();
+        machine.toggle_breakpoint(0x204);
+        machine.advance(0.0);
+        machine.advance(20.0);
+        assert!(machine.paused());
+        assert_eq!(machine.emulator().program_counter(), 0x204);
+        assert_eq!(machine.emulator().registers()[0], 2);
+
+        machine.step();
+        machine.step();
+        assert_eq!(machine.emulator().program_counter(), 0x204);
+        assert_eq!(machine.emulator().registers()[0], 3);
+
+        // Continuing runs the instruction under the breakpoint, then stops there again
+        machine.toggle_pause();
+        machine.advance(40.0);
+        machine.advance(60.0);
+        assert!(machine.paused());
+        assert_eq!(machine.emulator().registers()[0], 4);
+
+        machine.toggle_breakpoint(0x204);
+        assert!(machine.breakpoints().is_empty());
+        machine.poke(0x203, 0x10);
+        machine.toggle_pause();
+        machine.advance(80.0);
+        machine.advance(100.0);
+        assert!(!machine.paused());
+        assert_eq!(machine.emulator().registers()[0] % 0x10, 4);
+    }
+}
@@ -1,31 +1,16 @@
-use chip8_interpreter::Chip8Emulator;
-use yew::{prelude::*, virtual_dom::VNode};
-
-#[function_component(App)]
-fn app() -> Html {
-    let mut emu = Chip8Emulator::new();
-    let pong = include_bytes!("../../roms/PONG");
-    emu.load_data(pong);
-    let display: Vec<VNode> = emu
-        .to_string()
-        .split("\n")
-        .into_iter()
-        .map(|row| {
-            html! {
-                <p>{format!("{row}")}</p>
-            }
-        })
-        .collect();
-    html! {
-        <>
-            <h1>{ "Chip8 Emulator state" }</h1>
-            <div>
-                {display}
-            </div>
-        </>
-    }
-}
+mod app;
+mod appearance;
+mod clock;
+mod debugger;
+mod keypad;
+mod keys;
+mod library;
+mod machine;
+mod picker;
+mod saves;
+mod screen;
+mod slots;
 
 fn main() {
-    yew::Renderer::<App>::new().render();
+    yew::Renderer::<app::App>::new().render();
 }
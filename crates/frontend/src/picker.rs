@@ -0,0 +1,55 @@
+//! Selector for bundled and recently opened ROMs, plus a file input for new ones.
+
+use web_sys::{File, HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::library::{BUNDLED, Choice};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    /// Names of recently opened ROMs, most recent first
+    pub recent: Vec<AttrValue>,
+    pub selected: Choice,
+    pub on_choose: Callback<Choice>,
+    pub on_open: Callback<File>,
+}
+
+#[function_component(RomPicker)]
+pub fn rom_picker(props: &Props) -> Html {
+    let onchange = props.on_choose.reform(|event: Event| {
+        let select: HtmlSelectElement = event.target_unchecked_into();
+        Choice::parse(&select.value()).expect("options hold choice values")
+    });
+    let on_open = props.on_open.clone();
+    let on_file = Callback::from(move |event: Event| {
+        let input: HtmlInputElement = event.target_unchecked_into();
+        if let Some(file) = input.files().and_then(|files| files.get(0)) {
+            on_open.emit(file);
+        }
+        // Let the same file be picked again after switching away from it
+        input.set_value("");
+    });
+    let option = |choice: Choice, name: &str| {
+        html! {
+            <option value={choice.value()} selected={choice == props.selected}>{ name }</option>
+        }
+    };
+    html! {
+        <div class="picker">
+            <select {onchange}>
+                <optgroup label="Bundled">
+                    { for BUNDLED.iter().enumerate().map(|(idx, (name, _))| option(Choice::Bundled(idx), name)) }
+                </optgroup>
+                if !props.recent.is_empty() {
+                    <optgroup label="Recent">
+                        { for props.recent.iter().enumerate().map(|(idx, name)| option(Choice::Recent(idx), name)) }
+                    </optgroup>
+                }
+            </select>
+            <label class="open">
+                { "Open ROM…" }
+                <input type="file" onchange={on_file} />
+            </label>
+        </div>
+    }
+}
@@ -0,0 +1,197 @@
+//! Numbered save-state slots per ROM, kept in local storage with a thumbnail of the screen.
+
+use std::fmt::Write as _;
+
+use chip8_interpreter::{Chip8Emulator, RomHash, SCREEN_HEIGHT, SCREEN_WIDTH};
+use serde::{Deserialize, Serialize};
+
+/// Slots offered for each ROM
+pub const SLOTS: usize = 4;
+
+/// Local storage key for the [`SaveSlots`] of a ROM
+pub fn storage_key(rom: RomHash) -> String {
+    format!("chip8.saves.{rom}")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveSlot {
+    /// [`Chip8Emulator::save_state`] output
+    #[serde(with = "hex")]
+    pub state: Vec<u8>,
+    /// The display at save time, a bit per pixel, row-major and MSB first
+    #[serde(with = "hex")]
+    pub thumbnail: Vec<u8>,
+    /// Milliseconds since the Unix epoch
+    pub saved_at: f64,
+}
+
+impl SaveSlot {
+    pub fn capture(emulator: &Chip8Emulator, saved_at: f64) -> Self {
+        Self {
+            state: emulator.save_state(),
+            thumbnail: emulator.display_bytes().to_vec(),
+            saved_at,
+        }
+    }
+
+    fn lit(&self, x: usize, y: usize) -> bool {
+        let idx = y * SCREEN_WIDTH + x;
+        self.thumbnail
+            .get(idx / 8)
+            .is_some_and(|byte| byte & 0x80 >> (idx % 8) != 0)
+    }
+
+    /// SVG path drawing the lit pixels of the thumbnail in a 64x32 view box, one run per row
+    pub fn thumbnail_path(&self) -> String {
+        let mut path = String::new();
+        for y in 0..SCREEN_HEIGHT {
+            let mut x = 0;
+            while x < SCREEN_WIDTH {
+                if !self.lit(x, y) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < SCREEN_WIDTH && self.lit(x, y) {
+                    x += 1;
+                }
+                let _ = write!(path, "M{start} {y}h{}v1h-{}z", x - start, x - start);
+            }
+        }
+        path
+    }
+}
+
+/// The slots of one ROM, empty ones included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SaveSlots(Vec<Option<SaveSlot>>);
+
+impl Default for SaveSlots {
+    fn default() -> Self {
+        Self(vec![None; SLOTS])
+    }
+}
+
+impl SaveSlots {
+    pub fn get(&self, idx: usize) -> Option<&SaveSlot> {
+        self.0.get(idx)?.as_ref()
+    }
+
+    pub fn set(&mut self, idx: usize, slot: SaveSlot) {
+        if idx >= self.0.len() {
+            self.0.resize(idx + 1, None);
+        }
+        self.0[idx] = Some(slot);
+    }
+
+    /// Every slot in order, `None` where nothing is saved
+    pub fn iter(&self) -> impl Iterator<Item = Option<&SaveSlot>> {
+        self.0.iter().map(Option::as_ref)
+    }
+}
+
+/// Describe a save time for people, in their time zone where the browser can tell
+pub fn describe_time(saved_at: f64) -> String {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::new(&saved_at.into())
+            .to_locale_string("default", &wasm_bindgen::JsValue::UNDEFINED)
+            .into()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // Rendered on the server: plain UTC, from Howard Hinnant's `civil_from_days`
+        // Timestamps from `Date.now()` are whole milliseconds far inside the range of i64
+        #[allow(clippy::cast_possible_truncation)]
+        let secs = (saved_at / 1000.0) as i64;
+        let (days, secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        format!(
+            "{year}-{month:02}-{day:02} {:02}:{:02} UTC",
+            secs / 3600,
+            secs % 3600 / 60
+        )
+    }
+}
+
+/// Bytes as a hex string, half the size of a JSON array of numbers
+mod hex {
+    use std::fmt::Write as _;
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let text = bytes.iter().fold(String::new(), |mut text, byte| {
+            let _ = write!(text, "{byte:02x}");
+            text
+        });
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        // Slicing by byte offset below would panic inside a multi-byte character
+        if !text.is_ascii() {
+            return Err(D::Error::custom("hex digits must be ASCII"));
+        }
+        if text.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+        (0..text.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&text[idx..idx + 2], 16).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_hex() {
+        let parse = |text: &str| hex::deserialize(serde_json::Value::from(text));
+        assert_eq!(parse("00ff").unwrap(), [0x00, 0xFF]);
+        assert!(parse("0").is_err());
+        assert!(parse("zz").is_err());
+        assert!(parse("0é0").is_err());
+    }
+
+    #[test]
+    fn slots_round_trip_through_json() {
+        let mut emulator = Chip8Emulator::new();
+        // LD I, font 0; DRW V0, V0, 5
+        emulator.load_data(&[0xA0, 0x00, 0xD0, 0x05]).unwrap();
+        emulator.run_frame(2);
+
+        let mut slots = SaveSlots::default();
+        slots.set(2, SaveSlot::capture(&emulator, 1_790_000_000_000.0));
+        let json = serde_json::to_string(&slots).unwrap();
+        let slots: SaveSlots = serde_json::from_str(&json).unwrap();
+        assert_eq!(slots.iter().flatten().count(), 1);
+
+        let slot = slots.get(2).unwrap();
+        let mut restored = Chip8Emulator::new();
+        restored.load_state(&slot.state).unwrap();
+        assert_eq!(restored.state_hash(), emulator.state_hash());
+        // The top of the "0" glyph, then its sides
+        assert!(
+            slot.thumbnail_path()
+                .starts_with("M0 0h4v1h-4zM0 1h1v1h-1zM3 1h1v1h-1z")
+        );
+        assert_eq!(describe_time(slot.saved_at), "2026-09-21 14:13 UTC");
+        assert_eq!(
+            storage_key(emulator.rom_hash()),
+            format!("chip8.saves.{}", emulator.rom_hash())
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! Drawing the display onto a `<canvas>`.
+
+use chip8_interpreter::{
+    DisplayDamage, SCREEN_HEIGHT, SCREEN_WIDTH,
+    render::{Palette, Phosphor},
+};
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+/// Canvas size in pixels for a display scaled by `zoom`
+// The display is 64 x 32, and `u32::try_from` is not const
+#[allow(clippy::cast_possible_truncation)]
+pub const fn canvas_size(zoom: u32) -> (u32, u32) {
+    (SCREEN_WIDTH as u32 * zoom, SCREEN_HEIGHT as u32 * zoom)
+}
+
+/// Paint the pixels within `damage` as `zoom` x `zoom` squares in their palette colours
+pub fn draw(
+    canvas: &HtmlCanvasElement,
+    phosphor: &Phosphor,
+    palette: Palette,
+    zoom: u32,
+    damage: DisplayDamage,
+) {
+    let Some((left, top, width, height)) = damage.bounds() else {
+        return;
+    };
+    let Some(context) = canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .and_then(|context| context.dyn_into::<CanvasRenderingContext2d>().ok())
+    else {
+        return;
+    };
+    let zoom = f64::from(zoom);
+    // Display coordinates are below 64, so they convert exactly
+    #[allow(clippy::cast_precision_loss)]
+    let scaled = |pixels: usize| pixels as f64 * zoom;
+    context.set_fill_style_str(&palette.off.to_string());
+    context.fill_rect(scaled(left), scaled(top), scaled(width), scaled(height));
+    let intensities = phosphor.intensities();
+    for y in top..top + height {
+        for x in left..left + width {
+            let intensity = intensities[y * SCREEN_WIDTH + x];
+            if intensity > 0 {
+                context.set_fill_style_str(&palette.color(intensity).to_string());
+                context.fill_rect(scaled(x), scaled(y), zoom, zoom);
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Save and load buttons for each save slot, with what it holds.
+
+use chip8_interpreter::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use yew::prelude::*;
+
+use crate::saves::{SaveSlots, describe_time};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub slots: SaveSlots,
+    pub on_save: Callback<usize>,
+    pub on_load: Callback<usize>,
+}
+
+#[function_component(SlotList)]
+pub fn slot_list(props: &Props) -> Html {
+    let view_box = format!("0 0 {SCREEN_WIDTH} {SCREEN_HEIGHT}");
+    html! {
+        <ol class="slots">
+            { for props.slots.iter().enumerate().map(|(idx, slot)| html! {
+                <li>
+                    <svg class="thumbnail" viewBox={view_box.clone()}>
+                        if let Some(slot) = slot {
+                            <path d={slot.thumbnail_path()} />
+                        }
+                    </svg>
+                    <span class="slot-name">{ format!("Slot {}", idx + 1) }</span>
+                    <span class="saved-at">
+                        { slot.map_or_else(|| "Empty".to_string(), |slot| describe_time(slot.saved_at)) }
+                    </span>
+                    <button onclick={props.on_save.reform(move |_| idx)}>{ "Save" }</button>
+                    <button disabled={slot.is_none()} onclick={props.on_load.reform(move |_| idx)}>{ "Load" }</button>
+                </li>
+            }) }
+        </ol>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8_interpreter::Chip8Emulator;
+
+    use super::*;
+    use crate::saves::SaveSlot;
+
+    #[tokio::test]
+    async fn lists_filled_and_empty_slots() {
+        let html = yew::ServerRenderer::<SlotList>::with_props(|| {
+            let mut slots = SaveSlots::default();
+            slots.set(1, SaveSlot::capture(&Chip8Emulator::new(), 0.0));
+            Props {
+                slots,
+                on_save: Callback::noop(),
+                on_load: Callback::noop(),
+            }
+        })
+        .hydratable(false)
+        .render()
+        .await;
+        assert_eq!(html.matches(">Save</button>").count(), 4, "{html}");
+        assert_eq!(
+            html.matches(r#"<button disabled="disabled">Load</button>"#)
+                .count(),
+            3,
+            "{html}"
+        );
+        assert!(html.contains("Slot 2"), "{html}");
+        assert!(html.contains("1970-01-01 00:00 UTC"), "{html}");
+    }
+}
@@ -0,0 +1,72 @@
+-module(libchip8_interpreter).
+-export([
+    new/0,
+    load_rom/2,
+    tick/1,
+    run_frame/2,
+    run_frames/3,
+    tick_timers/1,
+    keypress/3,
+    get_display/1,
+    get_display_packed/1,
+    take_display_damage/1,
+    save_state/1,
+    load_state/2
+]).
+-nifs([
+    new/0,
+    load_rom/2,
+    tick/1,
+    run_frame/2,
+    run_frames/3,
+    tick_timers/1,
+    keypress/3,
+    get_display/1,
+    get_display_packed/1,
+    take_display_damage/1,
+    save_state/1,
+    load_state/2
+]).
+-on_load(init/0).
+
+%% Build with `cargo build --release -p chip8-interpreter --features rustler` and copy
+%% `target/release/libchip8_interpreter.so` into `priv/`.
+init() ->
+    Path = filename:join(code:priv_dir(ui), "libchip8_interpreter"),
+    erlang:load_nif(Path, 0).
+
+new() ->
+    erlang:nif_error(nif_library_not_loaded).
+
+load_rom(_Emulator, _Rom) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+tick(_Emulator) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+run_frame(_Emulator, _Ipf) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+run_frames(_Emulator, _Frames, _Ipf) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+tick_timers(_Emulator) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+keypress(_Emulator, _Key, _Pressed) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+get_display(_Emulator) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+get_display_packed(_Emulator) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+take_display_damage(_Emulator) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+save_state(_Emulator) ->
+    erlang:nif_error(nif_library_not_loaded).
+
+load_state(_Emulator, _State) ->
+    erlang:nif_error(nif_library_not_loaded).